@@ -1,55 +1,582 @@
-use crate::stringpool::StringPool;
+use crate::chunk_type::*;
+use crate::diagnostic::Diagnostics;
+use crate::stringpool::{to_usize, StringPool};
+use crate::{ParseError, ParseOptions};
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::vec::Vec;
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use deku::bitvec::{BitSlice, BitView, Msb0};
+use deku::ctx::Endian;
+use deku::error::NeedSize;
 use deku::prelude::*;
-use std::rc::Rc;
 
-#[derive(Debug, DekuRead)]
-pub(crate) struct BinaryXmlDocument {
-    #[allow(dead_code)] // `header` is used by `deku`
-    pub(crate) header: ChunkHeader,
+#[derive(Debug)]
+pub(crate) struct BinaryXmlDocument<'a> {
     pub(crate) string_pool: StringPool,
     pub(crate) resource_map: ResourceMap,
-    #[deku(bytes_read = "header.size -
-            u32::try_from(header.header_size).unwrap() -
-            string_pool.header.chunk_header.size -
-            resource_map.header.size")]
-    pub(crate) elements: Vec<XmlNode>,
+    pub(crate) elements: XmlNodes<'a>,
+}
+
+impl<'a> BinaryXmlDocument<'a> {
+    pub(crate) fn read(
+        input: &'a [u8],
+        options: &ParseOptions,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<Self, ParseError> {
+        let endian = detect_endian(input, ResourceType::Xml);
+        if !is_binary_xml(input, endian) {
+            return Err(ParseError::NotBinaryXml);
+        }
+        let (rest, header): (_, ChunkHeader) = read_chunk(input, endian).map_err(at(0))?;
+        let mut chunk_counts = BTreeMap::new();
+        count_chunk(&mut chunk_counts, RES_XML_TYPE);
+        if options.reject_trailing_data {
+            let size = usize::try_from(header.size).map_err(|_| ParseError::Overflow)?;
+            if let Some(trailing) = input.len().checked_sub(size).filter(|n| *n > 0) {
+                return Err(at(size)(ParseError::TrailingData(trailing)));
+            }
+        }
+
+        let offset = input.len() - rest.len();
+        let (rest, string_pool) =
+            StringPool::read_with_diagnostics(rest, options, endian, offset, diagnostics)
+                .map_err(at(offset))?;
+
+        count_chunk(&mut chunk_counts, RES_STRING_POOL_TYPE);
+        let rest = skip_null_chunks(rest, input.len() - rest.len(), endian, &mut chunk_counts)?;
+        let offset = input.len() - rest.len();
+        expect_chunk_type(rest, endian, ResourceType::XmlResourceMap).map_err(at(offset))?;
+        expect_chunk_size(rest, endian).map_err(at(offset))?;
+        let (rest, resource_map): (_, ResourceMap) =
+            read_chunk(rest, endian).map_err(at(offset))?;
+        count_chunk(&mut chunk_counts, RES_XML_RESOURCE_MAP_TYPE);
+
+        let offset = input.len() - rest.len();
+        let elements_size = usize::try_from(header.size)
+            .ok()
+            .and_then(|size| size.checked_sub(offset))
+            .ok_or(ParseError::Overflow)
+            .map_err(at(offset))?;
+        // A truncated document is only reported once the reader reaches
+        // the point where the data runs out.
+        let truncated = rest.len() < elements_size;
+        let elements_data = &rest[..elements_size.min(rest.len())];
+
+        Ok(Self {
+            string_pool,
+            resource_map,
+            elements: XmlNodes {
+                data: elements_data,
+                offset,
+                pending_eof: truncated,
+                truncated,
+                endian,
+                chunk_counts,
+            },
+        })
+    }
+}
+
+/// Iterator over the node chunks of a binary XML document, decoding each
+/// chunk as it is reached. Each node is returned along with the offset of
+/// its chunk in the document.
+#[derive(Debug, Default)]
+pub(crate) struct XmlNodes<'a> {
+    data: &'a [u8],
+    offset: usize,
+    // Whether the end of the data still has to be reported as an error.
+    pending_eof: bool,
+    // Whether the data is shorter than the size in the document header.
+    pub(crate) truncated: bool,
+    endian: Endian,
+    // Number of chunks of each type reached so far, including the ones
+    // before the nodes.
+    pub(crate) chunk_counts: BTreeMap<u16, usize>,
+}
+
+impl XmlNodes<'_> {
+    fn next_node(&mut self) -> Result<XmlNode, ParseError> {
+        let typ = peek_chunk_type(self.data, self.endian).inspect_err(|_| self.fuse())?;
+        count_chunk(&mut self.chunk_counts, typ);
+        match typ {
+            RES_XML_FIRST_CHUNK_TYPE..=RES_XML_CDATA_TYPE => {}
+            // Any node chunk is valid here, report the most common one.
+            found => {
+                // Step over a chunk with a plausible size so the reader can
+                // carry on after it when collecting diagnostics.
+                match chunk_size(self.data, self.endian) {
+                    Some(size) if (8..=self.data.len()).contains(&size) => {
+                        self.offset += size;
+                        self.data = &self.data[size..];
+                    }
+                    _ => self.fuse(),
+                }
+                return Err(ParseError::UnexpectedChunk {
+                    expected: ResourceType::XmlStartElement as u16,
+                    found,
+                });
+            }
+        }
+
+        // A size that could never step past the chunk is rejected like in
+        // every other chunk.
+        if let Some(header) = self.data.get(..8) {
+            let header_size = read_u16(self.endian, &header[2..4]);
+            let size = read_u32(self.endian, &header[4..8]);
+            check_chunk_size(header_size, size).inspect_err(|_| self.fuse())?;
+        }
+        let (node, size) = self.read_node().inspect_err(|_| self.fuse())?;
+
+        // Step over the whole chunk, including any bytes after the fields
+        // that were read. A chunk running past the end of the data is
+        // reported once the rest of it has been read.
+        let size = match self.data.get(size..) {
+            Some(_) => size,
+            None => {
+                self.pending_eof = true;
+                self.data.len()
+            }
+        };
+        self.offset += size;
+        self.data = &self.data[size..];
+        Ok(node)
+    }
+
+    /// Reads the node at the start of the data, with its extension after
+    /// the declared header size, and returns it with the size of its chunk.
+    fn read_node(&self) -> Result<(XmlNode, usize), ParseError> {
+        let (_, header): (_, XmlNodeHeader) = read_chunk(self.data, self.endian)?;
+        let header_size = header.chunk_header.header_size;
+        if header_size < NODE_HEADER_SIZE {
+            return Err(ParseError::InvalidChunkSize(u32::from(header_size)));
+        }
+        let size = to_usize(header.chunk_header.size)?;
+
+        let extension = self
+            .data
+            .get(usize::from(header_size)..)
+            .ok_or(ParseError::UnexpectedEof)?;
+        let bits = extension.view_bits::<Msb0>();
+        let (rest, element) = XmlNodeType::read(bits, (self.endian, header.chunk_header.typ))
+            .map_err(ParseError::DekuError)?;
+        // The fields may not run past the end of the chunk.
+        if self.data.len() - rest.len() / 8 > size {
+            return Err(ParseError::InvalidChunkSize(header.chunk_header.size));
+        }
+        Ok((XmlNode { header, element }, size))
+    }
+
+    /// Returns the offset in the document of the next chunk.
+    pub(crate) fn position(&self) -> usize {
+        self.offset
+    }
+
+    fn fuse(&mut self) {
+        self.data = &[];
+        self.pending_eof = false;
+    }
+}
+
+impl Iterator for XmlNodes<'_> {
+    type Item = Result<(usize, XmlNode), ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match skip_null_chunks(self.data, self.offset, self.endian, &mut self.chunk_counts) {
+            Ok(rest) => {
+                self.offset += self.data.len() - rest.len();
+                self.data = rest;
+            }
+            Err(e) => {
+                self.fuse();
+                return Some(Err(e));
+            }
+        }
+
+        if self.data.is_empty() {
+            if core::mem::take(&mut self.pending_eof) {
+                return Some(Err(at(self.offset)(ParseError::UnexpectedEof)));
+            }
+            return None;
+        }
+
+        let offset = self.offset;
+        match self.next_node() {
+            Ok(node) => Some(Ok((offset, node))),
+            Err(e) => Some(Err(at(offset)(e))),
+        }
+    }
+}
+
+///Struct representing the header of a chunk, as returned by [`chunks`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawChunkHeader {
+    offset: u64,
+    typ: u16,
+    header_size: u16,
+    size: u32,
+}
+
+impl RawChunkHeader {
+    ///Returns the offset of the chunk in the input.
+    #[must_use]
+    pub fn get_offset(&self) -> u64 {
+        self.offset
+    }
+
+    ///Returns the chunk type, one of the values in [`chunk_type`](crate::chunk_type).
+    #[must_use]
+    pub fn get_type(&self) -> u16 {
+        self.typ
+    }
+
+    ///Returns the size of the chunk header in bytes.
+    #[must_use]
+    pub fn get_header_size(&self) -> u16 {
+        self.header_size
+    }
+
+    ///Returns the total size of the chunk in bytes, including its header.
+    #[must_use]
+    pub fn get_size(&self) -> u32 {
+        self.size
+    }
+}
+
+///Walks the chunks of an Android binary XML without interpreting them.
+///
+///The document chunk comes first, followed by each chunk it contains in
+///file order. Chunks are not checked beyond their size fields, so this
+///also works on documents that fail to parse.
+///```rust
+///use axmldecoder::chunk_type::*;
+///use axmldecoder::chunks;
+///# use axmldecoder::ParseError;
+///let data = include_bytes!("../examples/AndroidManifest.xml");
+///let types = chunks(data)
+///    .map(|c| c.map(|c| c.get_type()))
+///    .collect::<Result<Vec<_>, _>>()?;
+///assert_eq!(
+///    types[..3],
+///    [RES_XML_TYPE, RES_STRING_POOL_TYPE, RES_XML_RESOURCE_MAP_TYPE]
+///);
+///# Ok::<(), ParseError>(())
+///```
+pub fn chunks(input: &[u8]) -> impl Iterator<Item = Result<RawChunkHeader, ParseError>> + '_ {
+    Chunks {
+        input,
+        offset: 0,
+        in_document: false,
+        endian: detect_endian(input, ResourceType::Xml),
+    }
+}
+
+struct Chunks<'a> {
+    input: &'a [u8],
+    offset: usize,
+    // Whether the document header has been read, after which the walk
+    // continues with the chunks inside it.
+    in_document: bool,
+    endian: Endian,
+}
+
+impl Chunks<'_> {
+    fn next_chunk(&mut self) -> Result<RawChunkHeader, ParseError> {
+        let rest = &self.input[self.offset..];
+        let header = rest.get(..8).ok_or(ParseError::UnexpectedEof)?;
+        let chunk = RawChunkHeader {
+            offset: self.offset as u64,
+            typ: read_u16(self.endian, &header[0..2]),
+            header_size: read_u16(self.endian, &header[2..4]),
+            size: read_u32(self.endian, &header[4..8]),
+        };
+
+        let size = expect_chunk_size(rest, self.endian)?;
+        let step = if self.in_document {
+            size
+        } else {
+            self.in_document = true;
+            usize::from(chunk.header_size).max(8)
+        };
+        self.offset += step;
+        Ok(chunk)
+    }
+}
+
+impl Iterator for Chunks<'_> {
+    type Item = Result<RawChunkHeader, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.input.len() {
+            return None;
+        }
+
+        let offset = self.offset;
+        let chunk = self.next_chunk();
+        if chunk.is_err() {
+            self.offset = self.input.len();
+        }
+        Some(chunk.map_err(at(offset)))
+    }
+}
+
+/// Wraps an error with the offset of the chunk it occurred in. An error
+/// that already has an offset keeps it.
+pub(crate) fn at(offset: usize) -> impl FnOnce(ParseError) -> ParseError {
+    move |e| match e {
+        ParseError::At { .. } => e,
+        e => ParseError::At {
+            offset: offset as u64,
+            source: Box::new(e),
+        },
+    }
+}
+
+/// Moves the offset of an error found in a document that starts at `base`
+/// in a larger input.
+pub(crate) fn offset_by(base: usize) -> impl FnOnce(ParseError) -> ParseError {
+    move |e| match e {
+        ParseError::At { offset, source } => ParseError::At {
+            offset: offset + base as u64,
+            source,
+        },
+        e => at(base)(e),
+    }
+}
+
+/// Reads the total size of the binary XML document at the start of
+/// `input`.
+pub(crate) fn document_size(input: &[u8]) -> Option<usize> {
+    chunk_size(input, detect_endian(input, ResourceType::Xml))
+}
+
+/// Checks that `input` starts with a binary XML chunk header. The total
+/// size may exceed the input so truncated documents are still reported
+/// where the data runs out.
+fn is_binary_xml(input: &[u8], endian: Endian) -> bool {
+    let Some(header) = input.get(..8) else {
+        return false;
+    };
+
+    let typ = read_u16(endian, &header[0..2]);
+    let header_size = read_u16(endian, &header[2..4]);
+    let size = read_u32(endian, &header[4..8]);
+    typ == ResourceType::Xml as u16 && header_size == 8 && size > 8
+}
+
+/// Reads the type of the chunk at the start of `input` without consuming
+/// it.
+fn peek_chunk_type(input: &[u8], endian: Endian) -> Result<u16, ParseError> {
+    input
+        .get(..2)
+        .map(|typ| read_u16(endian, typ))
+        .ok_or(ParseError::UnexpectedEof)
+}
+
+/// Steps over any `RES_NULL_TYPE` padding chunks at the start of `input`,
+/// which is at `offset` in the document, and returns the data after them.
+fn skip_null_chunks<'a>(
+    input: &'a [u8],
+    offset: usize,
+    endian: Endian,
+    chunk_counts: &mut BTreeMap<u16, usize>,
+) -> Result<&'a [u8], ParseError> {
+    let mut rest = input;
+    while matches!(peek_chunk_type(rest, endian), Ok(RES_NULL_TYPE)) {
+        count_chunk(chunk_counts, RES_NULL_TYPE);
+        // The size is at least a header, so this always moves forward.
+        let size =
+            expect_chunk_size(rest, endian).map_err(at(offset + input.len() - rest.len()))?;
+        rest = &rest[size..];
+    }
+    Ok(rest)
+}
+
+fn count_chunk(chunk_counts: &mut BTreeMap<u16, usize>, typ: u16) {
+    *chunk_counts.entry(typ).or_default() += 1;
+}
+
+/// Reads the total size of the chunk at the start of `input`.
+fn chunk_size(input: &[u8], endian: Endian) -> Option<usize> {
+    input
+        .get(4..8)
+        .map(|size| read_u32(endian, size))
+        .and_then(|size| usize::try_from(size).ok())
+}
+
+/// Checks that the chunk at the start of `input` is of type `expected`
+/// before its header is trusted.
+pub(crate) fn expect_chunk_type(
+    input: &[u8],
+    endian: Endian,
+    expected: ResourceType,
+) -> Result<(), ParseError> {
+    let found = peek_chunk_type(input, endian)?;
+    if found != expected as u16 {
+        return Err(ParseError::UnexpectedChunk {
+            expected: expected as u16,
+            found,
+        });
+    }
+
+    Ok(())
+}
+
+/// Checks that the size of the chunk at the start of `input` covers its
+/// header and fits in `input` before the size is trusted, returning it.
+fn expect_chunk_size(input: &[u8], endian: Endian) -> Result<usize, ParseError> {
+    let header = input.get(..8).ok_or(ParseError::UnexpectedEof)?;
+    let header_size = read_u16(endian, &header[2..4]);
+    let size = read_u32(endian, &header[4..8]);
+    check_chunk_size(header_size, size)?;
+
+    match usize::try_from(size) {
+        Ok(size) if size <= input.len() => Ok(size),
+        _ => Err(ParseError::UnexpectedEof),
+    }
+}
+
+/// Fails if `size` cannot hold the chunk header, which would keep a walk
+/// over the chunks from advancing.
+fn check_chunk_size(header_size: u16, size: u32) -> Result<(), ParseError> {
+    if size == 0 {
+        return Err(ParseError::ZeroSizeChunk);
+    }
+    if size < 8 || size < u32::from(header_size) {
+        return Err(ParseError::InvalidChunkSize(size));
+    }
+    Ok(())
+}
+
+/// Picks the byte order of the chunk at the start of `input` by checking
+/// which order gives the `expected` type. Binary XML is little-endian
+/// unless the type only matches when read as big-endian.
+pub(crate) fn detect_endian(input: &[u8], expected: ResourceType) -> Endian {
+    match input.get(..2) {
+        Some(typ)
+            if BigEndian::read_u16(typ) == expected as u16
+                && LittleEndian::read_u16(typ) != expected as u16 =>
+        {
+            Endian::Big
+        }
+        _ => Endian::Little,
+    }
+}
+
+/// Reads a `u16` from the start of `input` in the given byte order.
+pub(crate) fn read_u16(endian: Endian, input: &[u8]) -> u16 {
+    match endian {
+        Endian::Little => LittleEndian::read_u16(input),
+        Endian::Big => BigEndian::read_u16(input),
+    }
+}
+
+/// Reads a `u32` from the start of `input` in the given byte order.
+pub(crate) fn read_u32(endian: Endian, input: &[u8]) -> u32 {
+    match endian {
+        Endian::Little => LittleEndian::read_u32(input),
+        Endian::Big => BigEndian::read_u32(input),
+    }
+}
+
+/// Decodes a `T` from the start of `input` in the given byte order,
+/// returning it along with the bytes that follow it.
+pub(crate) fn read_chunk<'a, T>(
+    input: &'a [u8],
+    endian: Endian,
+) -> Result<(&'a [u8], T), ParseError>
+where
+    T: DekuRead<'a, Endian>,
+{
+    let bits = input.view_bits::<Msb0>();
+    let (rest, value) = T::read(bits, endian).map_err(ParseError::DekuError)?;
+    let consumed = (bits.len() - rest.len()) / 8;
+    Ok((&input[consumed..], value))
+}
+
+/// Splits `len` bytes off the front of `input`, failing with an
+/// `UnexpectedEof` error if not enough data is left.
+pub(crate) fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], ParseError> {
+    if input.len() < len {
+        return Err(ParseError::UnexpectedEof);
+    }
+
+    let (data, rest) = input.split_at(len);
+    *input = rest;
+    Ok(data)
 }
 
 #[derive(Debug, PartialEq, Clone, Copy, DekuRead, DekuWrite)]
-#[deku(type = "u16")]
+#[deku(
+    type = "u16",
+    endian = "endian",
+    ctx = "endian: Endian",
+    ctx_default = "Endian::Little"
+)]
+#[repr(u16)]
 pub(crate) enum ResourceType {
-    NullType = 0x000,
-    StringPool = 0x0001,
-    Table = 0x0002,
-    Xml = 0x0003,
-    XmlStartNameSpace = 0x0100,
-    XmlEndNameSpace = 0x101,
-    XmlStartElement = 0x0102,
-    XmlEndElement = 0x0103,
-    XmlCdata = 0x0104,
-    XmlLastChunk = 0x017f,
-    XmlResourceMap = 0x0180,
-    TablePackage = 0x0200,
-    TableType = 0x0201,
-    TableTypeSpec = 0x0202,
-    TableLibrary = 0x0203,
+    NullType = RES_NULL_TYPE,
+    StringPool = RES_STRING_POOL_TYPE,
+    Table = RES_TABLE_TYPE,
+    Xml = RES_XML_TYPE,
+    XmlStartNameSpace = RES_XML_START_NAMESPACE_TYPE,
+    XmlEndNameSpace = RES_XML_END_NAMESPACE_TYPE,
+    XmlStartElement = RES_XML_START_ELEMENT_TYPE,
+    XmlEndElement = RES_XML_END_ELEMENT_TYPE,
+    XmlCdata = RES_XML_CDATA_TYPE,
+    XmlLastChunk = RES_XML_LAST_CHUNK_TYPE,
+    XmlResourceMap = RES_XML_RESOURCE_MAP_TYPE,
+    TablePackage = RES_TABLE_PACKAGE_TYPE,
+    TableType = RES_TABLE_TYPE_TYPE,
+    TableTypeSpec = RES_TABLE_TYPE_SPEC_TYPE,
+    TableLibrary = RES_TABLE_LIBRARY_TYPE,
 }
 
 #[derive(Clone, Debug, DekuRead, DekuWrite)]
+#[deku(
+    endian = "endian",
+    ctx = "endian: Endian",
+    ctx_default = "Endian::Little"
+)]
 pub(crate) struct ChunkHeader {
     pub(crate) typ: ResourceType,
     pub(crate) header_size: u16,
     pub(crate) size: u32,
 }
 
-#[derive(Debug, DekuRead, DekuWrite)]
+#[derive(Debug, Clone, DekuRead, DekuWrite)]
+#[deku(
+    endian = "endian",
+    ctx = "endian: Endian",
+    ctx_default = "Endian::Little"
+)]
 pub(crate) struct ResourceMap {
     pub(crate) header: ChunkHeader,
     #[deku(count = "(header.size - u32::from(header.header_size)) / 4")]
     pub(crate) resource_ids: Vec<u32>,
 }
 
+impl ResourceMap {
+    pub(crate) fn get(&self, i: usize) -> Option<u32> {
+        self.resource_ids.get(i).copied()
+    }
+}
+
+/// Size of the header shared by every node chunk.
+pub(crate) const NODE_HEADER_SIZE: u16 = 16;
+/// Offset of the attributes from the start of the element extension, as
+/// written by the build tools.
+pub(crate) const ATTRIBUTE_START: u16 = 20;
+/// Size of each attribute of a start element.
+pub(crate) const ATTRIBUTE_SIZE: u16 = 20;
+
 #[derive(Debug, DekuRead, DekuWrite)]
+#[deku(
+    endian = "endian",
+    ctx = "endian: Endian",
+    ctx_default = "Endian::Little"
+)]
 pub(crate) struct XmlNode {
     pub(crate) header: XmlNodeHeader,
     #[deku(ctx = "header.chunk_header.typ")]
@@ -58,7 +585,11 @@ pub(crate) struct XmlNode {
 
 #[allow(clippy::enum_variant_names)]
 #[derive(Debug, DekuRead, DekuWrite)]
-#[deku(ctx = "typ: ResourceType", id = "typ")]
+#[deku(
+    ctx = "endian: Endian, typ: ResourceType",
+    id = "typ",
+    endian = "endian"
+)]
 pub(crate) enum XmlNodeType {
     #[deku(id = "ResourceType::XmlStartNameSpace")]
     XmlStartNameSpace(XmlStartNameSpace),
@@ -73,6 +604,11 @@ pub(crate) enum XmlNodeType {
 }
 
 #[derive(Debug, DekuRead, DekuWrite)]
+#[deku(
+    endian = "endian",
+    ctx = "endian: Endian",
+    ctx_default = "Endian::Little"
+)]
 pub(crate) struct XmlNodeHeader {
     pub(crate) chunk_header: ChunkHeader,
     pub(crate) line_no: u32,
@@ -80,18 +616,33 @@ pub(crate) struct XmlNodeHeader {
 }
 
 #[derive(Debug, DekuRead, DekuWrite)]
+#[deku(
+    endian = "endian",
+    ctx = "endian: Endian",
+    ctx_default = "Endian::Little"
+)]
 pub(crate) struct XmlStartNameSpace {
     pub(crate) prefix: u32,
     pub(crate) uri: u32,
 }
 
 #[derive(Debug, DekuRead, DekuWrite)]
+#[deku(
+    endian = "endian",
+    ctx = "endian: Endian",
+    ctx_default = "Endian::Little"
+)]
 pub(crate) struct XmlEndNameSpace {
     pub(crate) prefix: u32,
     pub(crate) uri: u32,
 }
 
 #[derive(Debug, DekuRead, DekuWrite)]
+#[deku(
+    endian = "endian",
+    ctx = "endian: Endian",
+    ctx_default = "Endian::Little"
+)]
 pub(crate) struct XmlAttrExt {
     pub(crate) ns: u32,
     pub(crate) name: u32,
@@ -104,32 +655,35 @@ pub(crate) struct XmlAttrExt {
 }
 
 #[derive(Debug, DekuRead, DekuWrite)]
+#[deku(
+    endian = "endian",
+    ctx = "endian: Endian",
+    ctx_default = "Endian::Little"
+)]
 pub(crate) struct ResourceValue {
     pub(crate) size: u16,
     pub(crate) res: u8,
-    pub(crate) data_type: ResourceValueType,
+    // Kept as a byte so that values of unknown types can still be read.
+    pub(crate) data_type: u8,
     pub(crate) data: u32,
 }
 
 impl ResourceValue {
-    pub(crate) fn get_value(&self, string_pool: &StringPool) -> Rc<String> {
-        match &self.data_type {
-            ResourceValueType::String => string_pool
-                .get(usize::try_from(self.data).unwrap())
-                .unwrap(),
-            ResourceValueType::Dec => Rc::new(self.data.to_string()),
-            ResourceValueType::Hex => Rc::new(format!("0x{}", self.data)),
-            ResourceValueType::Boolean => Rc::new(match self.data {
-                0 => "false".to_string(),
-                _ => "true".to_string(),
-            }),
-            n => Rc::new(format!("ResourceValueType::{:?}/{}", n, self.data)),
-        }
+    /// Returns the type of the value, or `None` if the type byte is not
+    /// one this crate knows.
+    pub(crate) fn get_type(&self) -> Option<ResourceValueType> {
+        let (_, data_type) = ResourceValueType::from_bytes((&[self.data_type], 0)).ok()?;
+        Some(data_type)
     }
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
-#[deku(type = "u8")]
+#[derive(Debug, PartialEq, Clone, Copy, DekuRead, DekuWrite)]
+#[deku(
+    type = "u8",
+    endian = "endian",
+    ctx = "endian: Endian",
+    ctx_default = "Endian::Little"
+)]
 pub(crate) enum ResourceValueType {
     Null = 0x00,
     Reference = 0x01,
@@ -148,6 +702,11 @@ pub(crate) enum ResourceValueType {
 }
 
 #[derive(Debug, DekuRead, DekuWrite)]
+#[deku(
+    endian = "endian",
+    ctx = "endian: Endian",
+    ctx_default = "Endian::Little"
+)]
 pub(crate) struct XmlAttribute {
     pub(crate) ns: u32,
     pub(crate) name: u32,
@@ -156,19 +715,74 @@ pub(crate) struct XmlAttribute {
 }
 
 #[derive(Debug, DekuRead, DekuWrite)]
+#[deku(
+    endian = "endian",
+    ctx = "endian: Endian",
+    ctx_default = "Endian::Little"
+)]
 pub(crate) struct XmlStartElement {
     pub(crate) attr_ext: XmlAttrExt,
-    #[deku(count = "attr_ext.attribute_count")]
+    #[deku(reader = "read_attributes(deku::rest, attr_ext, endian)")]
     pub(crate) attributes: Vec<XmlAttribute>,
 }
 
+/// Reads the attributes of a start element, which begin `attribute_start`
+/// bytes into the element extension and are `attribute_size` bytes apart.
+/// Any bytes in between are skipped.
+fn read_attributes<'a>(
+    rest: &'a BitSlice<u8, Msb0>,
+    ext: &XmlAttrExt,
+    endian: Endian,
+) -> Result<(&'a BitSlice<u8, Msb0>, Vec<XmlAttribute>), DekuError> {
+    let count = ext.attribute_count;
+    if count == 0 {
+        return Ok((rest, Vec::new()));
+    }
+
+    let gap = attribute_gap(ext.attribute_start, ATTRIBUTE_START)?;
+    let padding = attribute_gap(ext.attribute_size, ATTRIBUTE_SIZE)?;
+    let mut rest = skip_bytes(rest, gap)?;
+    let mut attributes = Vec::with_capacity(usize::from(count));
+    for _ in 0..count {
+        let (after, attribute) = XmlAttribute::read(rest, endian)?;
+        rest = skip_bytes(after, padding)?;
+        attributes.push(attribute);
+    }
+    Ok((rest, attributes))
+}
+
+/// Returns how many bytes `value` is past `minimum`, failing if a field of
+/// the attribute layout is smaller than what it has to hold.
+fn attribute_gap(value: u16, minimum: u16) -> Result<usize, DekuError> {
+    value.checked_sub(minimum).map(usize::from).ok_or_else(|| {
+        DekuError::Parse(format!(
+            "attribute layout of {value} bytes is below {minimum}"
+        ))
+    })
+}
+
+fn skip_bytes(rest: &BitSlice<u8, Msb0>, len: usize) -> Result<&BitSlice<u8, Msb0>, DekuError> {
+    rest.get(len * 8..)
+        .ok_or_else(|| DekuError::Incomplete(NeedSize::new(len * 8)))
+}
+
 #[derive(Debug, DekuRead, DekuWrite)]
+#[deku(
+    endian = "endian",
+    ctx = "endian: Endian",
+    ctx_default = "Endian::Little"
+)]
 pub(crate) struct XmlEndElement {
     pub(crate) ns: u32,
     pub(crate) name: u32,
 }
 
 #[derive(Debug, DekuRead, DekuWrite)]
+#[deku(
+    endian = "endian",
+    ctx = "endian: Endian",
+    ctx_default = "Endian::Little"
+)]
 pub(crate) struct XmlCdata {
     pub(crate) data: u32,
     pub(crate) typed_data: ResourceValue,
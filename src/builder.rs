@@ -0,0 +1,321 @@
+use std::sync::Arc;
+
+use crate::encoder::encode_document;
+use crate::manifest::ANDROID_NAMESPACE;
+use crate::resources::FrameworkResources;
+use crate::stringpool::StringEncoding;
+use crate::value::AttributeValue;
+use crate::xml::{Attribute, Element, Node, XmlDocument};
+use crate::EncodeError;
+
+///Builds an Android binary XML document in code.
+///
+///Elements are started and ended in document order, and attributes are
+///added to the element that was started most recently. The string pool
+///and namespace declarations are generated by [`DocumentBuilder::build`].
+///```rust
+///use axmldecoder::{AttributeValue, DocumentBuilder};
+///use std::sync::Arc;
+///
+///let mut builder = DocumentBuilder::new();
+///builder
+///    .start_element("manifest")
+///    .attribute(None, "package", AttributeValue::String(Arc::new("com.example".to_string())))
+///    .end_element();
+///let xml = builder.build().unwrap();
+///
+///let mut encoded = Vec::new();
+///xml.encode(&mut encoded).unwrap();
+///```
+#[derive(Debug, Default)]
+pub struct DocumentBuilder {
+    // Elements that have been started but not ended, innermost last.
+    open: Vec<Element>,
+    root: Option<Element>,
+    resources: FrameworkResources,
+}
+
+impl DocumentBuilder {
+    ///Creates a builder for an empty document.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///Creates a builder that looks up the resource IDs of attributes in
+    ///the Android namespace in `resources`, for attributes missing from
+    ///the built-in table.
+    #[must_use]
+    pub fn with_resources(resources: FrameworkResources) -> Self {
+        Self {
+            resources,
+            ..Self::default()
+        }
+    }
+
+    ///Starts an element inside the current one, or the root element if
+    ///none has been started yet.
+    ///
+    ///Does nothing once the root element has been ended.
+    pub fn start_element(&mut self, name: &str) -> &mut Self {
+        if self.root.is_none() {
+            self.open.push(Element::new(Arc::new(name.to_string())));
+        }
+        self
+    }
+
+    ///Adds an attribute to the current element. `namespace` is the URI of
+    ///the attribute's namespace, if it has one.
+    ///
+    ///Attributes in the Android namespace get the resource ID of the
+    ///framework attribute with the same name, which Android needs to
+    ///recognize them. See [`DocumentBuilder::with_resources`] for names
+    ///the built-in table does not know.
+    ///
+    ///Does nothing if no element is open.
+    pub fn attribute(
+        &mut self,
+        namespace: Option<&str>,
+        name: &str,
+        value: AttributeValue,
+    ) -> &mut Self {
+        if let Some(e) = self.open.last_mut() {
+            let mut attribute = Attribute::new(
+                namespace.map(|ns| Arc::new(ns.to_string())),
+                Arc::new(name.to_string()),
+                value,
+            );
+            if namespace == Some(ANDROID_NAMESPACE) {
+                attribute.resource_id = self.resources.id_for_name(name);
+            }
+            e.typed_attributes.push(attribute);
+        }
+        self
+    }
+
+    ///Ends the current element.
+    ///
+    ///Does nothing if no element is open.
+    pub fn end_element(&mut self) -> &mut Self {
+        if let Some(e) = self.open.pop() {
+            match self.open.last_mut() {
+                Some(parent) => parent.children.push(Node::Element(e)),
+                None => self.root = Some(e),
+            }
+        }
+        self
+    }
+
+    ///Builds the document, ending any elements that are still open.
+    ///
+    ///Every namespace used by an attribute is declared on the root
+    ///element. The Android namespace gets the `android` prefix, and the
+    ///others are numbered from `ns0` in the order they are first used.
+    ///
+    /// # Errors
+    ///
+    /// Will return `EncodeError` if the document cannot be represented in
+    /// the binary format, or an attribute in the Android namespace has no
+    /// known resource ID
+    pub fn build(mut self) -> Result<XmlDocument, EncodeError> {
+        while !self.open.is_empty() {
+            self.end_element();
+        }
+
+        let mut root = self.root;
+        if let Some(root) = &mut root {
+            if let Some(name) = missing_resource_id(root) {
+                return Err(EncodeError::UnknownAndroidAttribute(name.to_string()));
+            }
+
+            let mut namespaces = Vec::new();
+            collect_namespaces(root, &mut namespaces);
+            let mut others = 0;
+            root.namespace_declarations = namespaces
+                .into_iter()
+                .map(|uri| {
+                    let prefix = if uri.as_str() == ANDROID_NAMESPACE {
+                        "android".to_string()
+                    } else {
+                        let prefix = format!("ns{others}");
+                        others += 1;
+                        prefix
+                    };
+                    (uri, Arc::new(prefix))
+                })
+                .collect();
+        }
+
+        // Going through the binary format fills in everything the parser
+        // derives, such as qualified attribute names and the string pool.
+        let mut data = Vec::new();
+        encode_document(root.as_ref(), StringEncoding::Utf16, None, false, &mut data)?;
+        crate::parse(&data).map_err(EncodeError::Parse)
+    }
+}
+
+/// Returns the name of the first attribute in the Android namespace that
+/// has no resource ID, which Android would ignore.
+fn missing_resource_id(e: &Element) -> Option<&str> {
+    let attribute = e.typed_attributes.iter().find(|a| {
+        a.namespace.as_deref().map(String::as_str) == Some(ANDROID_NAMESPACE)
+            && a.resource_id.is_none()
+    });
+    if let Some(attribute) = attribute {
+        return Some(attribute.get_local_name());
+    }
+
+    e.get_children().iter().find_map(|child| match child {
+        Node::Element(child) => missing_resource_id(child),
+        _ => None,
+    })
+}
+
+fn collect_namespaces(e: &Element, namespaces: &mut Vec<Arc<String>>) {
+    for attr in &e.typed_attributes {
+        if let Some(ns) = &attr.namespace {
+            if !namespaces.contains(ns) {
+                namespaces.push(ns.clone());
+            }
+        }
+    }
+
+    for child in e.get_children() {
+        if let Node::Element(child) = child {
+            collect_namespaces(child, namespaces);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The root element of a built document.
+    fn root_of(xml: &XmlDocument) -> &Element {
+        let Some(Node::Element(root)) = xml.get_root() else {
+            panic!("missing root element");
+        };
+        root
+    }
+
+    #[test]
+    fn test_build() {
+        let mut builder = DocumentBuilder::new();
+        builder
+            .start_element("manifest")
+            .attribute(
+                None,
+                "package",
+                AttributeValue::String(Arc::new("com.example".to_string())),
+            )
+            .attribute(
+                Some(ANDROID_NAMESPACE),
+                "versionCode",
+                AttributeValue::Integer(3),
+            )
+            .start_element("application")
+            .attribute(
+                Some(ANDROID_NAMESPACE),
+                "debuggable",
+                AttributeValue::Boolean(true),
+            )
+            .end_element()
+            .end_element();
+        let xml = builder.build().unwrap();
+
+        let root = root_of(&xml);
+        assert_eq!(root.get_tag(), "manifest");
+        assert_eq!(
+            root.get_attribute("android:versionCode")
+                .unwrap()
+                .get_value(),
+            &AttributeValue::Integer(3)
+        );
+        assert!(matches!(
+            &root.get_children()[..],
+            [Node::Element(e)] if e.get_tag() == "application"
+        ));
+
+        let version_code = root.get_attribute("android:versionCode").unwrap();
+        assert_eq!(version_code.resource_id, Some(0x0101_021b));
+        let name = xml.get_string_index("versionCode").unwrap();
+        assert_eq!(xml.get_attribute_resource_id(name), Some(0x0101_021b));
+
+        let mut encoded = Vec::new();
+        xml.encode(&mut encoded).unwrap();
+        let reparsed = crate::parse(&encoded).unwrap();
+        assert_eq!(reparsed.get_root(), xml.get_root());
+    }
+
+    #[test]
+    fn test_custom_namespace() {
+        let mut builder = DocumentBuilder::new();
+        builder.start_element("root").attribute(
+            Some("http://example.com"),
+            "a",
+            AttributeValue::Hex(0x10),
+        );
+        let xml = builder.build().unwrap();
+
+        let root = root_of(&xml);
+        assert_eq!(
+            root.get_namespace_declarations().collect::<Vec<_>>(),
+            [("ns0", "http://example.com")]
+        );
+        assert!(root.get_attribute("ns0:a").is_some());
+    }
+
+    #[test]
+    fn test_unknown_android_attribute() {
+        let mut builder = DocumentBuilder::new();
+        builder
+            .start_element("manifest")
+            .attribute(
+                Some(ANDROID_NAMESPACE),
+                "compileSdkVersion",
+                AttributeValue::Integer(33),
+            )
+            .start_element("application")
+            .attribute(
+                Some(ANDROID_NAMESPACE),
+                "futureAttribute",
+                AttributeValue::Boolean(true),
+            );
+        assert!(matches!(
+            builder.build(),
+            Err(EncodeError::UnknownAndroidAttribute(name)) if name == "futureAttribute"
+        ));
+
+        let mut resources = FrameworkResources::new();
+        resources.insert(0x0101_0fff, "futureAttribute");
+        let mut builder = DocumentBuilder::with_resources(resources);
+        builder.start_element("manifest").attribute(
+            Some(ANDROID_NAMESPACE),
+            "futureAttribute",
+            AttributeValue::Boolean(true),
+        );
+        let xml = builder.build().unwrap();
+        let name = xml.get_string_index("futureAttribute").unwrap();
+        assert_eq!(xml.get_attribute_resource_id(name), Some(0x0101_0fff));
+    }
+
+    #[test]
+    fn test_namespace_prefixes() {
+        let mut builder = DocumentBuilder::new();
+        builder
+            .start_element("root")
+            .attribute(Some(ANDROID_NAMESPACE), "label", AttributeValue::Integer(1))
+            .attribute(Some("http://example.com"), "a", AttributeValue::Integer(2));
+        let xml = builder.build().unwrap();
+
+        let root = root_of(&xml);
+        assert_eq!(
+            root.get_namespace_declarations().collect::<Vec<_>>(),
+            [
+                ("android", ANDROID_NAMESPACE),
+                ("ns0", "http://example.com")
+            ]
+        );
+    }
+}
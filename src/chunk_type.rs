@@ -0,0 +1,23 @@
+//!Chunk type values found in the header of every chunk, named after their
+//!definitions in the Android framework's `ResourceTypes.h`.
+
+pub const RES_NULL_TYPE: u16 = 0x0000;
+pub const RES_STRING_POOL_TYPE: u16 = 0x0001;
+pub const RES_TABLE_TYPE: u16 = 0x0002;
+pub const RES_XML_TYPE: u16 = 0x0003;
+
+///The first of the chunk types used for XML nodes.
+pub const RES_XML_FIRST_CHUNK_TYPE: u16 = 0x0100;
+pub const RES_XML_START_NAMESPACE_TYPE: u16 = 0x0100;
+pub const RES_XML_END_NAMESPACE_TYPE: u16 = 0x0101;
+pub const RES_XML_START_ELEMENT_TYPE: u16 = 0x0102;
+pub const RES_XML_END_ELEMENT_TYPE: u16 = 0x0103;
+pub const RES_XML_CDATA_TYPE: u16 = 0x0104;
+///The last of the chunk types reserved for XML nodes.
+pub const RES_XML_LAST_CHUNK_TYPE: u16 = 0x017f;
+pub const RES_XML_RESOURCE_MAP_TYPE: u16 = 0x0180;
+
+pub const RES_TABLE_PACKAGE_TYPE: u16 = 0x0200;
+pub const RES_TABLE_TYPE_TYPE: u16 = 0x0201;
+pub const RES_TABLE_TYPE_SPEC_TYPE: u16 = 0x0202;
+pub const RES_TABLE_LIBRARY_TYPE: u16 = 0x0203;
@@ -0,0 +1,80 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::ParseError;
+
+///Struct representing a non-fatal problem found by
+///[`parse_with_diagnostics`](crate::parse_with_diagnostics).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    offset: u64,
+    message: String,
+}
+
+impl Diagnostic {
+    ///Returns the offset of the chunk the problem was found in.
+    #[must_use]
+    pub fn get_offset(&self) -> u64 {
+        self.offset
+    }
+
+    ///Returns a description of the problem.
+    #[must_use]
+    pub fn get_message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.offset)
+    }
+}
+
+/// Decides whether recoverable errors fail the parse or are collected as
+/// [`Diagnostic`]s.
+#[derive(Debug, Default)]
+pub(crate) struct Diagnostics {
+    collected: Option<Vec<Diagnostic>>,
+}
+
+impl Diagnostics {
+    pub(crate) fn collecting() -> Self {
+        Self {
+            collected: Some(Vec::new()),
+        }
+    }
+
+    /// Returns `e` in strict mode. Otherwise records it and returns `Ok`,
+    /// leaving the caller to carry on with a fallback value. The offset is
+    /// taken from `e` when it has been wrapped with the chunk offset.
+    pub(crate) fn recover(&mut self, e: ParseError) -> Result<(), ParseError> {
+        let Some(collected) = &mut self.collected else {
+            return Err(e);
+        };
+
+        let (offset, e) = match e {
+            ParseError::At { offset, source } => (offset, *source),
+            e => (0, e),
+        };
+        collected.push(Diagnostic {
+            offset,
+            message: e.to_string(),
+        });
+        Ok(())
+    }
+
+    /// Records `e` without failing in strict mode, for problems that do not
+    /// affect the parsed document.
+    pub(crate) fn warn(&mut self, e: ParseError) {
+        if self.collected.is_some() {
+            // Recovering never fails while collecting.
+            let _ = self.recover(e);
+        }
+    }
+
+    pub(crate) fn into_vec(self) -> Vec<Diagnostic> {
+        self.collected.unwrap_or_default()
+    }
+}
@@ -0,0 +1,995 @@
+use deku::ctx::Endian;
+use deku::prelude::*;
+use std::collections::HashMap;
+use std::io::Write;
+
+use crate::binaryxml::{
+    ChunkHeader, ResourceMap, ResourceType, ResourceValue, ResourceValueType, XmlAttrExt,
+    XmlAttribute, XmlCdata, XmlEndElement, XmlEndNameSpace, XmlNode, XmlNodeHeader, XmlNodeType,
+    XmlStartElement, XmlStartNameSpace, ATTRIBUTE_SIZE, ATTRIBUTE_START, NODE_HEADER_SIZE,
+};
+use crate::stringpool::{
+    StringEncoding, StringPool, StringPoolHeader, SORTED_FLAG, STRINGPOOL_HEADER_SIZE, UTF8_FLAG,
+};
+use crate::value::AttributeValue;
+use crate::xml::{Attribute, Element, Node, XmlDocument};
+use crate::{EncodeError, EncodeOptions};
+
+const NO_INDEX: u32 = u32::MAX;
+const CHUNK_HEADER_SIZE: u16 = 8;
+const RESOURCE_VALUE_SIZE: u16 = 8;
+
+impl XmlDocument {
+    ///Encodes the document back to Android's binary XML format.
+    ///
+    ///The string pool is rebuilt from the strings used by the document and
+    ///keeps the encoding of the original string pool. See
+    ///[`XmlDocument::encode_with_options`] to keep the original strings
+    ///instead.
+    ///
+    /// # Errors
+    ///
+    /// Will return `EncodeError` if the document cannot be represented in
+    /// the binary format or writing to `output` fails.
+    ///```rust
+    ///# use axmldecoder::ParseError;
+    ///let data = include_bytes!("../examples/AndroidManifest.xml");
+    ///let xml = axmldecoder::parse(data)?;
+    ///
+    ///let mut encoded = Vec::new();
+    ///xml.encode(&mut encoded).unwrap();
+    ///assert_eq!(axmldecoder::parse(&encoded)?.get_root(), xml.get_root());
+    ///# Ok::<(), ParseError>(())
+    ///```
+    pub fn encode<W: Write>(&self, output: &mut W) -> Result<(), EncodeError> {
+        self.encode_with_options(output, &EncodeOptions::default())
+    }
+
+    ///Encodes the document back to Android's binary XML format with the
+    ///given [`EncodeOptions`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `EncodeError` if the document cannot be represented in
+    /// the binary format or writing to `output` fails.
+    ///```rust
+    ///use axmldecoder::EncodeOptions;
+    ///# use axmldecoder::ParseError;
+    ///let data = include_bytes!("../examples/AndroidManifest.xml");
+    ///let xml = axmldecoder::parse(data)?;
+    ///
+    ///let options = EncodeOptions {
+    ///    reuse_string_pool: true,
+    ///    ..EncodeOptions::default()
+    ///};
+    ///let mut encoded = Vec::new();
+    ///xml.encode_with_options(&mut encoded, &options).unwrap();
+    ///assert!(axmldecoder::parse(&encoded)?.get_strings().eq(xml.get_strings()));
+    ///# Ok::<(), ParseError>(())
+    ///```
+    pub fn encode_with_options<W: Write>(
+        &self,
+        output: &mut W,
+        options: &EncodeOptions,
+    ) -> Result<(), EncodeError> {
+        let root = match self.get_root() {
+            Some(Node::Element(root)) => Some(root),
+            _ => None,
+        };
+        let base = options.reuse_string_pool.then_some(self);
+        encode_document(
+            root,
+            self.string_pool.encoding,
+            base,
+            options.sort_strings,
+            output,
+        )
+    }
+}
+
+/// Encodes a document with the given root element, building a string pool
+/// with `encoding` from the strings it uses. The strings and resource map
+/// of `base` are kept at their original indices if it is given, otherwise
+/// the strings are sorted if `sort_strings` is set.
+pub(crate) fn encode_document<W: Write>(
+    root: Option<&Element>,
+    encoding: StringEncoding,
+    base: Option<&XmlDocument>,
+    sort_strings: bool,
+    output: &mut W,
+) -> Result<(), EncodeError> {
+    let mut string_pool = base
+        .map(StringPoolBuilder::from_document)
+        .unwrap_or_default();
+
+    // Attribute names with a resource ID have to come first in the
+    // string pool, in the same order as the resource map.
+    if let Some(root) = root {
+        string_pool.add_resource_names(root)?;
+    }
+
+    // New resource names are appended, so they only line up with the
+    // resource map if every original string has a resource ID.
+    if let Some(base) = base {
+        let resource_count = base.resource_map.resource_ids.len();
+        if string_pool.resource_ids.len() > resource_count
+            && base.string_pool.len() != resource_count
+        {
+            return encode_document(root, encoding, None, sort_strings, output);
+        }
+    }
+
+    // The indices of the strings are only known once all of them have
+    // been added, so the body is encoded again after sorting.
+    if sort_strings && base.is_none() {
+        if let Some(root) = root {
+            encode_element(root, NO_INDEX, &mut string_pool, &mut Vec::new())?;
+        }
+        string_pool.sort();
+    }
+
+    let mut body = Vec::new();
+    if let Some(root) = root {
+        encode_element(root, NO_INDEX, &mut string_pool, &mut body)?;
+    }
+
+    // A reused pool that gained no strings is written back unchanged.
+    let original = match base {
+        Some(base) if string_pool.strings.len() == base.string_pool.len() => {
+            encode_original_pool(&base.string_pool)?
+        }
+        _ => None,
+    };
+    let string_pool_data = match original {
+        Some(data) => data,
+        None => string_pool.encode_string_pool(encoding)?,
+    };
+    let resource_map = string_pool.encode_resource_map()?;
+
+    let header = ChunkHeader {
+        typ: ResourceType::Xml,
+        header_size: CHUNK_HEADER_SIZE,
+        size: to_u32(
+            usize::from(CHUNK_HEADER_SIZE)
+                + string_pool_data.len()
+                + resource_map.len()
+                + body.len(),
+        )?,
+    };
+
+    output
+        .write_all(&header.to_bytes().map_err(EncodeError::DekuError)?)
+        .map_err(EncodeError::Io)?;
+    output
+        .write_all(&string_pool_data)
+        .map_err(EncodeError::Io)?;
+    output.write_all(&resource_map).map_err(EncodeError::Io)?;
+    output.write_all(&body).map_err(EncodeError::Io)?;
+
+    Ok(())
+}
+
+fn encode_element(
+    e: &Element,
+    comment: u32,
+    string_pool: &mut StringPoolBuilder,
+    output: &mut Vec<u8>,
+) -> Result<(), EncodeError> {
+    for (uri, prefix) in &e.namespace_declarations {
+        let element = XmlNodeType::XmlStartNameSpace(XmlStartNameSpace {
+            prefix: string_pool.intern(prefix)?,
+            uri: string_pool.intern(uri)?,
+        });
+        write_node(
+            output,
+            ResourceType::XmlStartNameSpace,
+            e.line_number,
+            NO_INDEX,
+            element,
+        )?;
+    }
+
+    let name = string_pool.intern(e.get_tag())?;
+
+    let mut attributes = Vec::with_capacity(e.typed_attributes.len());
+    for attr in &e.typed_attributes {
+        attributes.push(encode_attribute(attr, string_pool)?);
+    }
+
+    let element = XmlNodeType::XmlStartElement(XmlStartElement {
+        attr_ext: XmlAttrExt {
+            ns: NO_INDEX,
+            name,
+            attribute_start: ATTRIBUTE_START,
+            attribute_size: ATTRIBUTE_SIZE,
+            attribute_count: u16::try_from(attributes.len()).map_err(|_| EncodeError::Overflow)?,
+            id_index: 0,
+            class_index: 0,
+            style_index: 0,
+        },
+        attributes,
+    });
+    write_node(
+        output,
+        ResourceType::XmlStartElement,
+        e.line_number,
+        comment,
+        element,
+    )?;
+
+    // A comment is stored on the header of the node that follows it. One
+    // that is not followed by a node cannot be written.
+    let mut child_comment = NO_INDEX;
+    for child in e.get_children() {
+        match child {
+            Node::Comment(c) => child_comment = string_pool.intern(c.get_data())?,
+            Node::Element(child) => {
+                encode_element(child, child_comment, string_pool, output)?;
+                child_comment = NO_INDEX;
+            }
+            Node::Cdata(cdata) => {
+                let element = XmlNodeType::XmlCdata(XmlCdata {
+                    data: string_pool.intern(cdata.get_data())?,
+                    typed_data: match cdata.get_typed_value() {
+                        Some(value) => encode_value(value, string_pool)?,
+                        None => ResourceValue {
+                            size: RESOURCE_VALUE_SIZE,
+                            res: 0,
+                            data_type: ResourceValueType::Null as u8,
+                            data: 0,
+                        },
+                    },
+                });
+                write_node(
+                    output,
+                    ResourceType::XmlCdata,
+                    cdata.line_number,
+                    child_comment,
+                    element,
+                )?;
+                child_comment = NO_INDEX;
+            }
+        }
+    }
+
+    let element = XmlNodeType::XmlEndElement(XmlEndElement { ns: NO_INDEX, name });
+    write_node(
+        output,
+        ResourceType::XmlEndElement,
+        e.end_line_number,
+        comment,
+        element,
+    )?;
+
+    for (uri, prefix) in e.namespace_declarations.iter().rev() {
+        let element = XmlNodeType::XmlEndNameSpace(XmlEndNameSpace {
+            prefix: string_pool.intern(prefix)?,
+            uri: string_pool.intern(uri)?,
+        });
+        write_node(
+            output,
+            ResourceType::XmlEndNameSpace,
+            e.end_line_number,
+            NO_INDEX,
+            element,
+        )?;
+    }
+
+    Ok(())
+}
+
+fn encode_attribute(
+    attr: &Attribute,
+    string_pool: &mut StringPoolBuilder,
+) -> Result<XmlAttribute, EncodeError> {
+    let ns = match &attr.namespace {
+        Some(ns) => string_pool.intern(ns)?,
+        None => NO_INDEX,
+    };
+
+    let name = match attr.resource_id {
+        Some(resource_id) => string_pool.resource_name(&attr.local_name, resource_id)?,
+        None => string_pool.intern_name(&attr.local_name)?,
+    };
+
+    let mut typed_value = encode_value(attr.get_value(), string_pool)?;
+    // Any non-zero word reads back as true, so keep the one that was read.
+    if *attr.get_value() == AttributeValue::Boolean(true)
+        && attr.get_raw_type() == ResourceValueType::Boolean as u8
+        && attr.get_raw_data() != 0
+    {
+        typed_value.data = attr.get_raw_data();
+    }
+    let raw_value = match attr.get_raw_value() {
+        Some(raw_value) => string_pool.intern(raw_value)?,
+        None if typed_value.data_type == ResourceValueType::String as u8 => typed_value.data,
+        None => NO_INDEX,
+    };
+
+    Ok(XmlAttribute {
+        ns,
+        name,
+        raw_value,
+        typed_value,
+    })
+}
+
+fn encode_value(
+    value: &AttributeValue,
+    string_pool: &mut StringPoolBuilder,
+) -> Result<ResourceValue, EncodeError> {
+    let string = match value {
+        AttributeValue::String(s) => string_pool.intern(s)?,
+        _ => NO_INDEX,
+    };
+    // Raw values are written back with their type byte, known or not.
+    let (data_type, data) = value.to_raw(string);
+
+    Ok(ResourceValue {
+        size: RESOURCE_VALUE_SIZE,
+        res: 0,
+        data_type,
+        data,
+    })
+}
+
+fn write_node(
+    output: &mut Vec<u8>,
+    typ: ResourceType,
+    line_no: u32,
+    comment: u32,
+    element: XmlNodeType,
+) -> Result<(), EncodeError> {
+    let node = XmlNode {
+        header: XmlNodeHeader {
+            chunk_header: ChunkHeader {
+                typ,
+                header_size: NODE_HEADER_SIZE,
+                size: 0,
+            },
+            line_no,
+            comment,
+        },
+        element,
+    };
+
+    let mut bytes = node.to_bytes().map_err(EncodeError::DekuError)?;
+    let size = to_u32(bytes.len())?;
+    bytes[4..8].copy_from_slice(&size.to_le_bytes());
+
+    output.extend_from_slice(&bytes);
+    Ok(())
+}
+
+/// Returns the string pool chunk of `pool` exactly as it was read, or
+/// `None` if its layout cannot be reproduced from the parts that were
+/// kept.
+fn encode_original_pool(pool: &StringPool) -> Result<Option<Vec<u8>>, EncodeError> {
+    let header = &pool.header;
+    let to_usize = |n: u32| usize::try_from(n).map_err(|_| EncodeError::Overflow);
+    let offsets_end = usize::from(STRINGPOOL_HEADER_SIZE) + pool.offsets.len() * 4;
+    let string_count = to_usize(header.string_count)?;
+    let size = offsets_end + pool.string_data.len() + pool.style_data.len();
+    if pool.endian != Endian::Little
+        || pool.encoding != StringEncoding::from_flags(header.flags)
+        || header.chunk_header.header_size != STRINGPOOL_HEADER_SIZE
+        || pool.offsets.len() != string_count + to_usize(header.style_count)?
+        || (string_count > 0 && to_usize(header.string_start)? != offsets_end)
+        || to_usize(header.chunk_header.size)? != size
+    {
+        return Ok(None);
+    }
+
+    let mut data = header.to_bytes().map_err(EncodeError::DekuError)?;
+    for offset in &pool.offsets {
+        data.extend_from_slice(&offset.to_le_bytes());
+    }
+    data.extend_from_slice(&pool.string_data);
+    data.extend_from_slice(&pool.style_data);
+    Ok(Some(data))
+}
+
+fn to_u32(input: usize) -> Result<u32, EncodeError> {
+    u32::try_from(input).map_err(|_| EncodeError::Overflow)
+}
+
+#[derive(Default)]
+struct StringPoolBuilder {
+    strings: Vec<String>,
+    indices: HashMap<String, u32>,
+    name_indices: HashMap<String, u32>,
+    resource_indices: HashMap<(String, u32), u32>,
+    resource_ids: Vec<u32>,
+    sorted: bool,
+}
+
+impl StringPoolBuilder {
+    /// Creates a builder that starts out with the strings and resource map
+    /// of `document`.
+    fn from_document(document: &XmlDocument) -> Self {
+        let mut builder = Self::default();
+        let resource_ids = &document.resource_map.resource_ids;
+        for (i, s) in document.string_pool.get_strings().enumerate() {
+            if let Some(resource_id) = resource_ids.get(i) {
+                builder
+                    .resource_indices
+                    .insert((s.to_string(), *resource_id), u32::try_from(i).unwrap());
+            }
+            builder.strings.push(s.to_string());
+        }
+        builder.resource_ids.clone_from(resource_ids);
+
+        // Other uses of a string, such as comments, may point at a resource
+        // name. Strings without a resource ID are preferred.
+        let resource_count = resource_ids.len().min(builder.strings.len());
+        for i in (resource_count..builder.strings.len()).chain(0..resource_count) {
+            builder
+                .indices
+                .entry(builder.strings[i].clone())
+                .or_insert(u32::try_from(i).unwrap());
+        }
+        builder
+    }
+
+    fn add_resource_names(&mut self, e: &Element) -> Result<(), EncodeError> {
+        for attr in &e.typed_attributes {
+            if let Some(resource_id) = attr.resource_id {
+                self.resource_name(&attr.local_name, resource_id)?;
+            }
+        }
+
+        for child in e.get_children() {
+            if let Node::Element(child) = child {
+                self.add_resource_names(child)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn resource_name(&mut self, name: &str, resource_id: u32) -> Result<u32, EncodeError> {
+        let key = (name.to_string(), resource_id);
+        if let Some(index) = self.resource_indices.get(&key) {
+            return Ok(*index);
+        }
+
+        let index = to_u32(self.strings.len())?;
+        self.strings.push(name.to_string());
+        self.resource_ids.push(resource_id);
+        self.resource_indices.insert(key, index);
+        Ok(index)
+    }
+
+    fn intern(&mut self, s: &str) -> Result<u32, EncodeError> {
+        if let Some(index) = self.indices.get(s) {
+            return Ok(*index);
+        }
+
+        let index = to_u32(self.strings.len())?;
+        self.strings.push(s.to_string());
+        self.indices.insert(s.to_string(), index);
+        Ok(index)
+    }
+
+    /// Interns the name of an attribute without a resource ID, which must
+    /// not be a string that the resource map applies to.
+    fn intern_name(&mut self, s: &str) -> Result<u32, EncodeError> {
+        let index = self.intern(s)?;
+        if usize::try_from(index).unwrap() >= self.resource_ids.len() {
+            return Ok(index);
+        }
+        if let Some(index) = self.name_indices.get(s) {
+            return Ok(*index);
+        }
+
+        let index = to_u32(self.strings.len())?;
+        self.strings.push(s.to_string());
+        self.name_indices.insert(s.to_string(), index);
+        Ok(index)
+    }
+
+    /// Sorts the strings after the resource names by their UTF-16 code
+    /// units. The resource names have to stay first, so the pool is only
+    /// marked as sorted if there are none.
+    fn sort(&mut self) {
+        let resource_count = self.resource_ids.len();
+        let mut strings = self.strings.split_off(resource_count);
+        strings.sort_by(|a, b| a.encode_utf16().cmp(b.encode_utf16()));
+
+        // Both maps only hold one index for each string past the resource
+        // names.
+        let positions: HashMap<&str, u32> = strings
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (s.as_str(), u32::try_from(resource_count + i).unwrap()))
+            .collect();
+        for (s, index) in self.indices.iter_mut().chain(&mut self.name_indices) {
+            if usize::try_from(*index).unwrap() >= resource_count {
+                *index = positions[s.as_str()];
+            }
+        }
+
+        self.strings.extend(strings);
+        self.sorted = resource_count == 0;
+    }
+
+    fn encode_string_pool(&self, encoding: StringEncoding) -> Result<Vec<u8>, EncodeError> {
+        let mut offsets = Vec::with_capacity(self.strings.len() * 4);
+        let mut string_data = Vec::new();
+        for s in &self.strings {
+            offsets.extend_from_slice(&to_u32(string_data.len())?.to_le_bytes());
+            match encoding {
+                StringEncoding::Utf8 => encode_utf8_string(s, &mut string_data)?,
+                StringEncoding::Utf16 => encode_utf16_string(s, &mut string_data)?,
+            }
+        }
+        while string_data.len() % 4 != 0 {
+            string_data.push(0);
+        }
+
+        let string_start = usize::from(STRINGPOOL_HEADER_SIZE) + offsets.len();
+        let header = StringPoolHeader {
+            chunk_header: ChunkHeader {
+                typ: ResourceType::StringPool,
+                header_size: STRINGPOOL_HEADER_SIZE,
+                size: to_u32(string_start + string_data.len())?,
+            },
+            string_count: to_u32(self.strings.len())?,
+            style_count: 0,
+            flags: match encoding {
+                StringEncoding::Utf8 => UTF8_FLAG,
+                StringEncoding::Utf16 => 0,
+            } | if self.sorted { SORTED_FLAG } else { 0 },
+            string_start: to_u32(string_start)?,
+            style_start: 0,
+        };
+
+        let mut data = header.to_bytes().map_err(EncodeError::DekuError)?;
+        data.extend_from_slice(&offsets);
+        data.extend_from_slice(&string_data);
+        Ok(data)
+    }
+
+    fn encode_resource_map(&self) -> Result<Vec<u8>, EncodeError> {
+        let resource_map = ResourceMap {
+            header: ChunkHeader {
+                typ: ResourceType::XmlResourceMap,
+                header_size: CHUNK_HEADER_SIZE,
+                size: to_u32(usize::from(CHUNK_HEADER_SIZE) + self.resource_ids.len() * 4)?,
+            },
+            resource_ids: self.resource_ids.clone(),
+        };
+
+        resource_map.to_bytes().map_err(EncodeError::DekuError)
+    }
+}
+
+fn encode_utf8_string(s: &str, output: &mut Vec<u8>) -> Result<(), EncodeError> {
+    encode_utf8_length(s.encode_utf16().count(), output)?;
+    encode_utf8_length(s.len(), output)?;
+    output.extend_from_slice(s.as_bytes());
+    output.push(0);
+    Ok(())
+}
+
+fn encode_utf8_length(len: usize, output: &mut Vec<u8>) -> Result<(), EncodeError> {
+    if len > 0x7FFF {
+        return Err(EncodeError::Overflow);
+    }
+
+    // Lengths above 127 continue into a second byte, signalled by the
+    // high bit of the first.
+    let len = len.to_be_bytes();
+    let (high, low) = (len[len.len() - 2], len[len.len() - 1]);
+    if high > 0 || low > 0x7F {
+        output.push(high | 0x80);
+    }
+    output.push(low);
+    Ok(())
+}
+
+fn encode_utf16_string(s: &str, output: &mut Vec<u8>) -> Result<(), EncodeError> {
+    let units: Vec<u16> = s.encode_utf16().collect();
+
+    let len = to_u32(units.len())?;
+    if len > 0x7FFF_FFFF {
+        return Err(EncodeError::Overflow);
+    }
+
+    // Lengths above 32767 continue into a second u16, signalled by the
+    // high bit of the first.
+    let len = len.to_be_bytes();
+    let high = u16::from_be_bytes([len[0], len[1]]);
+    let low = u16::from_be_bytes([len[2], len[3]]);
+    if high > 0 || low > 0x7FFF {
+        output.extend_from_slice(&(high | 0x8000).to_le_bytes());
+    }
+    output.extend_from_slice(&low.to_le_bytes());
+
+    for unit in units {
+        output.extend_from_slice(&unit.to_le_bytes());
+    }
+    output.extend_from_slice(&[0, 0]);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::FrameworkResources;
+    use crate::xml::{Cdata, Comment};
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    /// Every fixture in `examples/`, with the path it was read from.
+    fn examples() -> Vec<(PathBuf, Vec<u8>)> {
+        let mut dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        dir.push("examples");
+        std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| {
+                let path = entry.unwrap().path();
+                let data = std::fs::read(&path).unwrap();
+                (path, data)
+            })
+            .collect()
+    }
+
+    /// The root element of a parsed fixture.
+    fn root_of(xml: &XmlDocument) -> &Element {
+        let Some(Node::Element(root)) = xml.get_root() else {
+            panic!("missing root element");
+        };
+        root
+    }
+
+    /// The root element of a parsed fixture, for tests that edit the tree.
+    fn root_of_mut(xml: &mut XmlDocument) -> &mut Element {
+        let Some(Node::Element(root)) = &mut xml.root else {
+            panic!("missing root element");
+        };
+        root
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for (path, data) in examples() {
+            let xml = crate::parse(&data).unwrap();
+
+            let mut encoded = Vec::new();
+            xml.encode(&mut encoded)
+                .unwrap_or_else(|_| panic!("{} failed to encode", path.display()));
+            let reparsed = crate::parse(&encoded)
+                .unwrap_or_else(|_| panic!("{} failed to reparse", path.display()));
+
+            assert_eq!(reparsed.get_root(), xml.get_root(), "{}", path.display());
+            assert_eq!(reparsed.to_xml_string(), xml.to_xml_string());
+
+            let options = EncodeOptions {
+                reuse_string_pool: true,
+                ..EncodeOptions::default()
+            };
+            let mut encoded = Vec::new();
+            xml.encode_with_options(&mut encoded, &options).unwrap();
+            let reparsed = crate::parse(&encoded).unwrap();
+            assert_eq!(reparsed.get_root(), xml.get_root(), "{}", path.display());
+            assert!(
+                reparsed.get_strings().eq(xml.get_strings()),
+                "{}",
+                path.display()
+            );
+        }
+    }
+
+    #[test]
+    fn test_encode_value_matches_input() {
+        fn check(e: &Element, string_pool: &mut StringPoolBuilder) {
+            for attr in e.get_typed_attributes() {
+                let value = encode_attribute(attr, string_pool).unwrap().typed_value;
+                assert_eq!(value.data_type, attr.get_raw_type(), "{}", attr.get_name());
+                // String indices depend on the rebuilt pool.
+                if !matches!(attr.get_value(), AttributeValue::String(_)) {
+                    assert_eq!(value.data, attr.get_raw_data(), "{}", attr.get_name());
+                }
+            }
+            for child in e.get_children() {
+                if let Node::Element(child) = child {
+                    check(child, string_pool);
+                }
+            }
+        }
+
+        for (_, data) in examples() {
+            let xml = crate::parse(&data).unwrap();
+            check(root_of(&xml), &mut StringPoolBuilder::default());
+        }
+    }
+
+    #[test]
+    fn test_typed_value_round_trip() {
+        let values = [
+            AttributeValue::Integer(-7),
+            AttributeValue::Float(1.5),
+            AttributeValue::Dimension(0x0000_1001),
+            AttributeValue::Fraction(0x0000_3200),
+            AttributeValue::Reference(0x7f02_0000),
+            AttributeValue::Boolean(false),
+            AttributeValue::Hex(0xdead_beef),
+            AttributeValue::String(Arc::new("typed string".to_string())),
+        ];
+
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let mut xml = crate::parse(data).unwrap();
+        let root = root_of_mut(&mut xml);
+        for (i, value) in values.iter().enumerate() {
+            root.typed_attributes.push(Attribute::new(
+                None,
+                Arc::new(format!("value{i}")),
+                value.clone(),
+            ));
+        }
+
+        let mut encoded = Vec::new();
+        xml.encode(&mut encoded).unwrap();
+        let reparsed = crate::parse(&encoded).unwrap();
+        let root = root_of(&reparsed);
+        for (i, value) in values.iter().enumerate() {
+            let attr = root.get_attribute(&format!("value{i}")).unwrap();
+            assert_eq!(attr.get_value(), value);
+        }
+
+        // Encoding the reparsed document again gives the same bytes.
+        let mut reencoded = Vec::new();
+        reparsed.encode(&mut reencoded).unwrap();
+        assert_eq!(reencoded, encoded);
+    }
+
+    #[test]
+    fn test_multi_line_comment() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let mut xml = crate::parse(data).unwrap();
+        let root = root_of_mut(&mut xml);
+        root.children.insert(
+            0,
+            Node::Comment(Comment {
+                data: Arc::new("first line\n  second line".to_string()),
+                line_number: 6,
+            }),
+        );
+
+        let mut encoded = Vec::new();
+        xml.encode(&mut encoded).unwrap();
+        let reparsed = crate::parse(&encoded).unwrap();
+
+        assert_eq!(reparsed.get_root(), xml.get_root());
+        assert!(reparsed
+            .to_xml_string()
+            .contains("\n  <!--first line\n  second line-->\n  <application "));
+    }
+
+    #[test]
+    fn test_cdata_typed_value() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let mut xml = crate::parse(data).unwrap();
+        let root = root_of_mut(&mut xml);
+        root.children.push(Node::Cdata(Cdata {
+            data: "42".to_string(),
+            typed_value: Some(AttributeValue::Integer(42)),
+            line_number: 12,
+            byte_range: 0..0,
+        }));
+        root.children.push(Node::Cdata(Cdata {
+            data: "plain".to_string(),
+            typed_value: None,
+            line_number: 13,
+            byte_range: 0..0,
+        }));
+
+        let mut encoded = Vec::new();
+        xml.encode(&mut encoded).unwrap();
+        let reparsed = crate::parse(&encoded).unwrap();
+        assert_eq!(reparsed.get_root(), xml.get_root());
+
+        let root = root_of(&reparsed);
+        let cdata: Vec<_> = root
+            .get_children()
+            .iter()
+            .filter_map(|child| match child {
+                Node::Cdata(cdata) => Some(cdata),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(cdata.len(), 2);
+        assert_eq!(cdata[0].get_data(), "42");
+        assert_eq!(
+            cdata[0].get_typed_value(),
+            Some(&AttributeValue::Integer(42))
+        );
+        assert_eq!(cdata[1].get_data(), "plain");
+        assert_eq!(cdata[1].get_typed_value(), None);
+    }
+
+    #[test]
+    fn test_deduplicate_strings() {
+        let value = || AttributeValue::String(Arc::new("same".to_string()));
+        let mut builder = crate::DocumentBuilder::new();
+        builder.start_element("manifest");
+        for _ in 0..3 {
+            builder
+                .start_element("item")
+                .attribute(None, "label", value())
+                .attribute(None, "same", value())
+                .end_element();
+        }
+        let xml = builder.build().unwrap();
+
+        let mut encoded = Vec::new();
+        xml.encode(&mut encoded).unwrap();
+        let xml = crate::parse(&encoded).unwrap();
+        let strings: Vec<&str> = xml.get_strings().collect();
+        assert_eq!(strings, ["manifest", "item", "label", "same"]);
+    }
+
+    #[test]
+    fn test_sort_strings() {
+        let options = EncodeOptions {
+            sort_strings: true,
+            ..EncodeOptions::default()
+        };
+        let mut builder = crate::DocumentBuilder::new();
+        builder
+            .start_element("manifest")
+            .attribute(
+                None,
+                "package",
+                AttributeValue::String(Arc::new("com.example".to_string())),
+            )
+            .start_element("application")
+            .attribute(
+                None,
+                "label",
+                AttributeValue::String(Arc::new("Example".to_string())),
+            )
+            .end_element();
+        let xml = builder.build().unwrap();
+
+        let mut encoded = Vec::new();
+        xml.encode_with_options(&mut encoded, &options).unwrap();
+        let sorted = crate::parse(&encoded).unwrap();
+        assert!(sorted.string_pool.is_sorted());
+        let strings: Vec<&str> = sorted.get_strings().collect();
+        assert_eq!(
+            strings,
+            [
+                "Example",
+                "application",
+                "com.example",
+                "label",
+                "manifest",
+                "package"
+            ]
+        );
+        assert_eq!(sorted.get_string_index("manifest"), Some(4));
+        assert_eq!(sorted.get_root(), xml.get_root());
+
+        // Attribute names with a resource ID keep their place at the start.
+        let data = include_bytes!("../examples/AndroidManifestWithComment.xml");
+        let xml = crate::parse(data).unwrap();
+        let mut encoded = Vec::new();
+        xml.encode_with_options(&mut encoded, &options).unwrap();
+        let sorted = crate::parse(&encoded).unwrap();
+        assert!(!sorted.string_pool.is_sorted());
+        assert_eq!(sorted.get_root(), xml.get_root());
+
+        let resource_count = sorted.resource_map.resource_ids.len();
+        let strings: Vec<&str> = sorted.get_strings().skip(resource_count).collect();
+        assert!(strings
+            .windows(2)
+            .all(|w| w[0].encode_utf16().lt(w[1].encode_utf16())));
+    }
+
+    #[test]
+    fn test_reuse_string_pool() {
+        let options = EncodeOptions {
+            reuse_string_pool: true,
+            ..EncodeOptions::default()
+        };
+        let data = include_bytes!("../examples/AndroidManifestWithComment.xml");
+        let mut xml = crate::parse(data).unwrap();
+
+        let mut encoded = Vec::new();
+        xml.encode(&mut encoded).unwrap();
+        let rebuilt = crate::parse(&encoded).unwrap();
+        assert!(!rebuilt.get_strings().eq(xml.get_strings()));
+
+        let mut encoded = Vec::new();
+        xml.encode_with_options(&mut encoded, &options).unwrap();
+        let reused = crate::parse(&encoded).unwrap();
+        assert!(reused.get_strings().eq(xml.get_strings()));
+        assert_eq!(reused.get_root(), xml.get_root());
+
+        let root = root_of_mut(&mut xml);
+        root.typed_attributes.push(Attribute::new(
+            None,
+            Arc::new("added".to_string()),
+            AttributeValue::String(Arc::new("new value".to_string())),
+        ));
+
+        let mut encoded = Vec::new();
+        xml.encode_with_options(&mut encoded, &options).unwrap();
+        let reused = crate::parse(&encoded).unwrap();
+        let strings: Vec<_> = reused.get_strings().collect();
+        assert_eq!(strings.len(), xml.get_string_count() + 2);
+        assert!(strings
+            .iter()
+            .copied()
+            .take(xml.get_string_count())
+            .eq(xml.get_strings()));
+        assert_eq!(strings[strings.len() - 2..], ["added", "new value"]);
+    }
+
+    #[test]
+    fn test_reuse_unchanged_string_pool() {
+        let options = EncodeOptions {
+            reuse_string_pool: true,
+            ..EncodeOptions::default()
+        };
+        for (_, data) in examples() {
+            let xml = crate::parse(&data).unwrap();
+            let mut encoded = Vec::new();
+            xml.encode_with_options(&mut encoded, &options).unwrap();
+
+            let pool_end = 8 + usize::try_from(xml.string_pool.header.chunk_header.size).unwrap();
+            assert_eq!(encoded[8..pool_end], data[8..pool_end]);
+        }
+
+        // The pool of this document has a style offset but no styles, and
+        // the rest is already written as it was read.
+        let data = include_bytes!("../examples/AndroidManifestNonZeroStyle.xml");
+        let xml = crate::parse(data).unwrap();
+        let mut encoded = Vec::new();
+        xml.encode_with_options(&mut encoded, &options).unwrap();
+        assert_eq!(encoded, data);
+    }
+
+    #[test]
+    fn test_reuse_string_pool_new_resource_name() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let mut xml = crate::parse(data).unwrap();
+        let root = root_of_mut(&mut xml);
+        let mut attribute = Attribute::new(
+            Some(Arc::new(crate::manifest::ANDROID_NAMESPACE.to_string())),
+            Arc::new("compileSdkVersion".to_string()),
+            AttributeValue::Integer(33),
+        );
+        attribute.resource_id = FrameworkResources::new().id_for_name("compileSdkVersion");
+        root.typed_attributes.push(attribute);
+
+        let mut encoded = Vec::new();
+        let options = EncodeOptions {
+            reuse_string_pool: true,
+            ..EncodeOptions::default()
+        };
+        xml.encode_with_options(&mut encoded, &options).unwrap();
+        let reparsed = crate::parse(&encoded).unwrap();
+        assert_eq!(
+            crate::ManifestInfo::from_document(&reparsed).compile_sdk_version,
+            Some(33)
+        );
+    }
+
+    #[test]
+    fn test_utf8_length() {
+        let mut output = Vec::new();
+        encode_utf8_length(0x7F, &mut output).unwrap();
+        encode_utf8_length(200, &mut output).unwrap();
+        assert_eq!(output, [0x7F, 0x80, 200]);
+    }
+
+    #[test]
+    fn test_utf16_length() {
+        let mut output = Vec::new();
+        encode_utf16_string(&"a".repeat(40000), &mut output).unwrap();
+        assert_eq!(output[..4], [0x00, 0x80, 0x40, 0x9C]);
+    }
+}
@@ -0,0 +1,446 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::value::AttributeValue;
+use crate::xml::{Cdata, Comment, Element, Node, XmlDocument};
+use crate::FormatOptions;
+
+const XML_DECLARATION: &str = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n";
+
+impl XmlDocument {
+    ///Serializes the document to indented XML text, starting with an XML
+    ///declaration.
+    ///
+    ///Empty elements are written as self-closing tags and attribute values
+    ///and text are escaped.
+    ///```rust
+    ///# use axmldecoder::ParseError;
+    ///let data = include_bytes!("../examples/AndroidManifest.xml");
+    ///let xml = axmldecoder::parse(data)?;
+    ///assert!(xml
+    ///    .to_xml_string()
+    ///    .starts_with("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<manifest "));
+    ///# Ok::<(), ParseError>(())
+    ///```
+    #[must_use]
+    pub fn to_xml_string(&self) -> String {
+        self.to_xml_string_with(&FormatOptions::default())
+    }
+
+    ///Serializes the document to XML text formatted according to `opts`.
+    ///```rust
+    ///use axmldecoder::FormatOptions;
+    ///# use axmldecoder::ParseError;
+    ///let data = include_bytes!("../examples/AndroidManifest.xml");
+    ///let xml = axmldecoder::parse(data)?;
+    ///let opts = FormatOptions {
+    ///    indent: 4,
+    ///    xml_declaration: false,
+    ///    ..FormatOptions::default()
+    ///};
+    ///assert!(xml.to_xml_string_with(&opts).starts_with("<manifest "));
+    ///# Ok::<(), ParseError>(())
+    ///```
+    #[must_use]
+    pub fn to_xml_string_with(&self, opts: &FormatOptions) -> String {
+        let mut output = String::new();
+        if opts.xml_declaration {
+            output.push_str(XML_DECLARATION);
+        }
+        if let Some(root) = self.get_root() {
+            format_node(root, 0, opts, &mut output);
+        }
+        output
+    }
+
+    ///Returns a reader over the text of [`XmlDocument::to_xml_string`],
+    ///which writes out one node at a time as it is read instead of
+    ///building the whole string.
+    ///```rust
+    ///# use axmldecoder::ParseError;
+    ///use std::io::Read;
+    ///let data = include_bytes!("../examples/AndroidManifest.xml");
+    ///let xml = axmldecoder::parse(data)?;
+    ///let mut text = String::new();
+    ///xml.xml_reader().read_to_string(&mut text)?;
+    ///assert_eq!(text, xml.to_xml_string());
+    ///# Ok::<(), Box<dyn std::error::Error>>(())
+    ///```
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn xml_reader(&self) -> XmlReader<'_> {
+        let opts = FormatOptions::default();
+        let buf = if opts.xml_declaration {
+            String::from(XML_DECLARATION)
+        } else {
+            String::new()
+        };
+        XmlReader {
+            root: self.get_root().as_ref(),
+            stack: Vec::new(),
+            opts,
+            buf,
+            pos: 0,
+        }
+    }
+
+    ///Returns the text content of the document without any markup, for
+    ///uses such as search indexing.
+    ///
+    ///Each element is written on its own line as its tag followed by the
+    ///values of its string attributes, and text is written on its own
+    ///line. Comments and values of other types, such as references, are
+    ///left out.
+    ///```rust
+    ///# use axmldecoder::ParseError;
+    ///let data = include_bytes!("../examples/AndroidManifest.xml");
+    ///let xml = axmldecoder::parse(data)?;
+    ///assert!(xml.to_plain_text().starts_with("manifest 1.0 org.t0t0.androguard.TC\n"));
+    ///# Ok::<(), ParseError>(())
+    ///```
+    #[must_use]
+    pub fn to_plain_text(&self) -> String {
+        let mut output = String::new();
+        if let Some(root) = self.get_root() {
+            push_text(root, &mut output);
+        }
+        output
+    }
+}
+
+///Reader over the XML text of a document, returned by
+///[`XmlDocument::xml_reader`].
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct XmlReader<'a> {
+    // The root node, until it has been written.
+    root: Option<&'a Node>,
+    // Elements whose end tag is still to be written, with the index of the
+    // next child to write.
+    stack: Vec<(&'a Element, usize)>,
+    opts: FormatOptions,
+    buf: String,
+    pos: usize,
+}
+
+#[cfg(feature = "std")]
+impl<'a> XmlReader<'a> {
+    /// Writes the next node or end tag to the buffer. Returns `false` once
+    /// the whole document has been written.
+    fn fill(&mut self) -> bool {
+        if let Some(root) = self.root.take() {
+            self.write_node(root, 0);
+            return true;
+        }
+
+        let level = self.stack.len().saturating_sub(1);
+        let Some((e, next)) = self.stack.last_mut() else {
+            return false;
+        };
+        let e: &'a Element = e;
+        match e.get_children().get(*next) {
+            Some(child) => {
+                *next += 1;
+                self.write_node(child, level + 1);
+            }
+            None => {
+                self.stack.pop();
+                format_end_tag(e, level, &self.opts, &mut self.buf);
+            }
+        }
+        true
+    }
+
+    fn write_node(&mut self, node: &'a Node, level: usize) {
+        match node {
+            Node::Element(e) => {
+                format_start_tag(e, level, &self.opts, &mut self.buf);
+                if !e.get_children().is_empty() {
+                    self.stack.push((e, 0));
+                }
+            }
+            Node::Cdata(e) => format_cdata(e, level, &self.opts, &mut self.buf),
+            Node::Comment(e) => format_comment(e, level, &self.opts, &mut self.buf),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::io::Read for XmlReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.pos == self.buf.len() {
+            self.buf.clear();
+            self.pos = 0;
+            if !self.fill() {
+                return Ok(0);
+            }
+        }
+
+        let pending = &self.buf.as_bytes()[self.pos..];
+        let n = pending.len().min(buf.len());
+        buf[..n].copy_from_slice(&pending[..n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+fn push_text(node: &Node, output: &mut String) {
+    match node {
+        Node::Element(e) => {
+            output.push_str(e.get_tag());
+            for attr in e.get_typed_attributes() {
+                if let AttributeValue::String(value) = attr.get_value() {
+                    output.push(' ');
+                    output.push_str(value);
+                }
+            }
+            output.push('\n');
+
+            for child in e.get_children() {
+                push_text(child, output);
+            }
+        }
+        Node::Cdata(e) => {
+            let data = e.get_data().trim();
+            if !data.is_empty() {
+                output.push_str(data);
+                output.push('\n');
+            }
+        }
+        Node::Comment(_) => {}
+    }
+}
+
+fn format_node(node: &Node, level: usize, opts: &FormatOptions, output: &mut String) {
+    match node {
+        Node::Element(e) => format_element(e, level, opts, output),
+        Node::Cdata(e) => format_cdata(e, level, opts, output),
+        Node::Comment(e) => format_comment(e, level, opts, output),
+    }
+}
+
+fn format_element(e: &Element, level: usize, opts: &FormatOptions, output: &mut String) {
+    format_start_tag(e, level, opts, output);
+    if e.get_children().is_empty() {
+        return;
+    }
+
+    for child in e.get_children() {
+        format_node(child, level + 1, opts, output);
+    }
+    format_end_tag(e, level, opts, output);
+}
+
+/// Writes the start tag of `e`, which is self-closing if it has no
+/// children.
+fn format_start_tag(e: &Element, level: usize, opts: &FormatOptions, output: &mut String) {
+    push_indent(level, opts, output);
+    output.push('<');
+    output.push_str(e.get_tag());
+
+    // The <manifest> element already carries every namespace as an
+    // attribute, other elements need their own declarations written out.
+    for (prefix, uri) in e.get_namespace_declarations() {
+        let key = format!("xmlns:{prefix}");
+        if !e.get_attributes().contains_key(&key) {
+            output.push(' ');
+            output.push_str(&key);
+            output.push_str("=\"");
+            push_escaped(uri, true, output);
+            output.push('"');
+        }
+    }
+
+    let mut attributes: Vec<_> = e.get_attributes().iter().collect();
+    if opts.sort_attributes {
+        attributes.sort_unstable_by_key(|(key, _)| *key);
+    }
+    for (key, val) in attributes {
+        output.push(' ');
+        output.push_str(key);
+        output.push_str("=\"");
+        push_escaped(val, true, output);
+        output.push('"');
+    }
+
+    if e.get_children().is_empty() {
+        output.push_str("/>\n");
+    } else {
+        output.push_str(">\n");
+    }
+}
+
+fn format_end_tag(e: &Element, level: usize, opts: &FormatOptions, output: &mut String) {
+    push_indent(level, opts, output);
+    output.push_str("</");
+    output.push_str(e.get_tag());
+    output.push_str(">\n");
+}
+
+fn format_comment(e: &Comment, level: usize, opts: &FormatOptions, output: &mut String) {
+    // Comments are written as is, including any newlines.
+    push_indent(level, opts, output);
+    output.push_str("<!--");
+    output.push_str(e.get_data());
+    output.push_str("-->\n");
+}
+
+fn format_cdata(e: &Cdata, level: usize, opts: &FormatOptions, output: &mut String) {
+    push_indent(level, opts, output);
+    push_escaped(e.get_data(), false, output);
+    output.push('\n');
+}
+
+fn push_indent(level: usize, opts: &FormatOptions, output: &mut String) {
+    output.push_str(&" ".repeat(level * opts.indent));
+}
+
+fn push_escaped(s: &str, is_attribute: bool, output: &mut String) {
+    for c in s.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' if is_attribute => output.push_str("&quot;"),
+            c => output.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_xml_string() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let xml = crate::parse(data).unwrap();
+
+        let expected = r#"<?xml version="1.0" encoding="utf-8"?>
+<manifest xmlns:android="http://schemas.android.com/apk/res/android" android:versionCode="1" android:versionName="1.0" package="org.t0t0.androguard.TC">
+  <application android:label="@0x7f040000" android:icon="@0x7f020000" android:debuggable="true">
+    <activity android:label="@0x7f040000" android:name="TCActivity">
+      <intent-filter>
+        <action android:name="android.intent.action.MAIN"/>
+        <category android:name="android.intent.category.LAUNCHER"/>
+      </intent-filter>
+    </activity>
+  </application>
+</manifest>
+"#;
+        assert_eq!(xml.to_xml_string(), expected);
+    }
+
+    #[test]
+    fn test_indent() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let xml = crate::parse(data).unwrap();
+
+        let two = xml.to_xml_string_with(&FormatOptions::default());
+        assert_eq!(two, xml.to_xml_string());
+        assert!(two.contains("\n    <activity "));
+
+        let four = xml.to_xml_string_with(&FormatOptions {
+            indent: 4,
+            ..FormatOptions::default()
+        });
+        assert!(four.contains("\n    <application "));
+        assert!(four.contains("\n        <activity "));
+        assert!(four.contains("\n                <action "));
+        assert!(four.ends_with("\n    </application>\n</manifest>\n"));
+
+        let bare = xml.to_xml_string_with(&FormatOptions {
+            xml_declaration: false,
+            ..FormatOptions::default()
+        });
+        assert!(bare.starts_with("<manifest "));
+        assert_eq!(
+            two,
+            format!("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n{bare}")
+        );
+    }
+
+    #[test]
+    fn test_sort_attributes() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let xml = crate::parse(data).unwrap();
+
+        let unsorted = xml.to_xml_string();
+        assert!(unsorted
+            .contains(r#"<activity android:label="@0x7f040000" android:name="TCActivity">"#));
+
+        let sorted = xml.to_xml_string_with(&FormatOptions {
+            sort_attributes: true,
+            xml_declaration: false,
+            ..FormatOptions::default()
+        });
+        assert!(sorted.contains(
+            r#"<application android:debuggable="true" android:icon="@0x7f020000" android:label="@0x7f040000">"#
+        ));
+        assert!(
+            sorted.contains(r#"<activity android:label="@0x7f040000" android:name="TCActivity">"#)
+        );
+        assert!(sorted.starts_with(
+            r#"<manifest android:versionCode="1" android:versionName="1.0" package="org.t0t0.androguard.TC" xmlns:android="http://schemas.android.com/apk/res/android">"#
+        ));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_xml_reader() {
+        use std::io::Read;
+
+        let mut examples = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        examples.push("examples");
+        for entry in std::fs::read_dir(examples).unwrap() {
+            let data = std::fs::read(entry.unwrap().path()).unwrap();
+            let xml = crate::parse(&data).unwrap();
+
+            let mut text = String::new();
+            xml.xml_reader().read_to_string(&mut text).unwrap();
+            assert_eq!(text, xml.to_xml_string());
+        }
+
+        // Reads smaller than a node are carried over to the next read.
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let xml = crate::parse(data).unwrap();
+        let mut reader = xml.xml_reader();
+        let mut text = Vec::new();
+        let mut buf = [0; 7];
+        loop {
+            let n = reader.read(&mut buf).unwrap();
+            if n == 0 {
+                break;
+            }
+            text.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(String::from_utf8(text).unwrap(), xml.to_xml_string());
+    }
+
+    #[test]
+    fn test_to_plain_text() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let xml = crate::parse(data).unwrap();
+
+        let expected = "manifest 1.0 org.t0t0.androguard.TC
+application
+activity TCActivity
+intent-filter
+action android.intent.action.MAIN
+category android.intent.category.LAUNCHER
+";
+        assert_eq!(xml.to_plain_text(), expected);
+    }
+
+    #[test]
+    fn test_escaping() {
+        let mut output = String::new();
+        push_escaped(r#"<a href="x">&</a>"#, true, &mut output);
+        assert_eq!(output, "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;");
+
+        let mut output = String::new();
+        push_escaped(r#"say "hi" & <bye>"#, false, &mut output);
+        assert_eq!(output, r#"say "hi" &amp; &lt;bye&gt;"#);
+    }
+}
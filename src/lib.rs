@@ -12,17 +12,67 @@
 //!is highly recommended if it is acceptable to link a 30MB `resources.arsc`
 //!file into your compiled binary.
 //!
+//![`parse`] takes the whole document as a byte slice, which can come
+//!from a memory-mapped file, for example with the `memmap2` crate. The
+//!parsed [`XmlDocument`] owns its strings and does not borrow from the
+//!input, so the mapping can be dropped as soon as parsing returns.
+//!
+//!To reuse existing XML tooling such as `quick-xml`, pass
+//!`XmlDocument::xml_reader` to its reader, or map each [`XmlEvent`] to
+//!the matching start, end, text or comment event.
+//!
+//!The optional `serde` feature derives `Serialize` for the parsed
+//!document so it can be exported to formats such as JSON. The optional
+//!`zip` feature adds `parse_apk_manifest` to read the manifest straight
+//!out of an APK.
+//!
+//!The `std` feature is enabled by default. Without it the crate is
+//!`no_std` and only needs `alloc`; the encoder, `DocumentBuilder` and
+//!`zip` support require `std`.
+//!
 //!Please file an issue with the relevant binary `AndroidManifest.xml` if
 //!if any issues are encountered.
 
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+// `deku`'s derive macros expand to code that trips this lint.
+#![allow(clippy::manual_div_ceil)]
+
+extern crate alloc;
+
 mod binaryxml;
+#[cfg(feature = "std")]
+mod builder;
+pub mod chunk_type;
+mod diagnostic;
+#[cfg(feature = "std")]
+mod encoder;
+mod format;
+mod manifest;
+mod reader;
+mod resources;
+mod select;
 mod stringpool;
+mod value;
 mod xml;
 
+use alloc::string::String;
+use alloc::vec::Vec;
 use thiserror::Error;
 
-use crate::binaryxml::BinaryXmlDocument;
-pub use crate::xml::{Cdata, Element, Node, XmlDocument};
+pub use crate::binaryxml::{chunks, RawChunkHeader};
+#[cfg(feature = "std")]
+pub use crate::builder::DocumentBuilder;
+pub use crate::diagnostic::Diagnostic;
+#[cfg(feature = "std")]
+pub use crate::format::XmlReader;
+pub use crate::manifest::{ManifestInfo, ManifestSummary};
+pub use crate::reader::{XmlEvent, XmlEventReader, XmlHandler};
+pub use crate::resources::FrameworkResources;
+pub use crate::stringpool::{NulHandling, PoolDiff, StringEncoding, StringPool, StyleSpan};
+pub use crate::value::{AttributeValue, Color, ColorFormat};
+#[cfg(not(feature = "std"))]
+pub use crate::xml::AttributeHasher;
+pub use crate::xml::{Attribute, AttributeMap, Cdata, Comment, Element, Node, XmlDocument};
 
 #[derive(Error, Debug)]
 pub enum ParseError {
@@ -38,11 +88,184 @@ pub enum ParseError {
     #[error("Unknown resource string: {0}")]
     UnknownResourceString(u32),
 
+    #[cfg(feature = "std")]
+    #[error(transparent)]
+    Io(std::io::Error),
+
+    #[error("unexpected end of data")]
+    UnexpectedEof,
+
+    #[error("integer overflow while computing an offset")]
+    Overflow,
+
+    #[error("string pool count {0} does not fit in its chunk")]
+    StringCountOutOfBounds(u32),
+
+    #[error("element nesting exceeds the maximum depth of {0}")]
+    DepthExceeded(usize),
+
+    #[error("start and end of elements do not match")]
+    UnbalancedElements,
+
+    #[error("invalid chunk size: {0}")]
+    InvalidChunkSize(u32),
+
+    #[error("chunk has a size of zero")]
+    ZeroSizeChunk,
+
+    #[error("string pool header offsets point past the end of the chunk")]
+    InconsistentHeader,
+
+    #[error("offset {position} points past the end of its chunk at {bound}")]
+    OffsetOutOfBounds { position: u64, bound: u64 },
+
+    #[error("attribute {0} is set more than once")]
+    DuplicateAttribute(String),
+
+    #[error("string pool has {0} styles but no style data")]
+    MissingStyleData(u32),
+
+    #[error("string pool has unknown flags set: {0:#010x}")]
+    UnknownStringPoolFlags(u32),
+
+    #[error("string contains a NUL character")]
+    InteriorNul,
+
+    #[error("{source} at offset {offset}")]
+    At {
+        offset: u64,
+        source: alloc::boxed::Box<ParseError>,
+    },
+
+    #[error("{0} bytes of data after the end of the document")]
+    TrailingData(usize),
+
+    #[error("input is not an Android binary XML document")]
+    NotBinaryXml,
+
+    #[error("expected chunk type 0x{expected:04x}, found 0x{found:04x}")]
+    UnexpectedChunk { expected: u16, found: u16 },
+
     #[error(transparent)]
-    Utf8StringParseError(std::string::FromUtf8Error),
+    Utf8StringParseError(alloc::string::FromUtf8Error),
 
     #[error(transparent)]
-    Utf16StringParseError(std::string::FromUtf16Error),
+    Utf16StringParseError(alloc::string::FromUtf16Error),
+
+    #[cfg(feature = "zip")]
+    #[error(transparent)]
+    Zip(zip::result::ZipError),
+}
+
+#[cfg(feature = "std")]
+#[derive(Error, Debug)]
+pub enum EncodeError {
+    #[error("encode error: {0}")]
+    DekuError(deku::DekuError),
+
+    #[error(transparent)]
+    Io(std::io::Error),
+
+    #[error("value too large for the binary XML format")]
+    Overflow,
+
+    #[error("encoded document could not be parsed: {0}")]
+    Parse(ParseError),
+
+    #[error("no resource ID is known for the Android attribute {0}")]
+    UnknownAndroidAttribute(String),
+}
+
+///Options controlling how a document is encoded by
+///[`XmlDocument::encode_with_options`].
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Default)]
+pub struct EncodeOptions {
+    ///Keep the strings of the original string pool at their indices and
+    ///only append the strings that are new, instead of rebuilding the pool
+    ///from the strings the document uses. This keeps the encoded document
+    ///close to the original.
+    ///
+    ///If the document uses no new strings, the original pool is written
+    ///back byte for byte, including its offsets and style data. The pool
+    ///is still rebuilt if new attribute names with a resource ID cannot be
+    ///placed with the existing ones.
+    pub reuse_string_pool: bool,
+    ///Sort the strings of a rebuilt string pool, so that documents with the
+    ///same strings get the same pool. Attribute names with a resource ID
+    ///stay at the start of the pool, and the pool is only marked as sorted
+    ///if there are none.
+    ///
+    ///Has no effect when the original string pool is reused.
+    pub sort_strings: bool,
+}
+
+///Options controlling how an Android binary XML is parsed.
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    ///Replace invalid UTF-8 or UTF-16 sequences in the string pool with
+    ///U+FFFD instead of failing.
+    pub lossy: bool,
+    ///The deepest element nesting that is accepted before failing with
+    ///[`ParseError::DepthExceeded`]. Defaults to 512.
+    pub max_depth: usize,
+    ///Fail with [`ParseError::TrailingData`] if there is data after the end
+    ///of the document, such as padding added by signing tools, instead of
+    ///ignoring it.
+    pub reject_trailing_data: bool,
+    ///Fail with [`ParseError::DuplicateAttribute`] if an element has two
+    ///attributes with the same name, instead of keeping both.
+    ///
+    ///[`parse_with_diagnostics`] always reports these.
+    pub reject_duplicate_attributes: bool,
+    ///Names of Android framework attributes, for attributes that are only
+    ///named through their resource ID. Defaults to the built-in table.
+    pub framework_resources: FrameworkResources,
+    ///Read the strings of every string pool with this encoding instead of
+    ///the one given by the pool flags, for files written by tools that set
+    ///the wrong flag.
+    pub force_encoding: Option<StringEncoding>,
+    ///How NUL characters inside strings are handled. Defaults to keeping
+    ///them.
+    pub interior_nul: NulHandling,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            lossy: false,
+            max_depth: 512,
+            reject_trailing_data: false,
+            reject_duplicate_attributes: false,
+            framework_resources: FrameworkResources::new(),
+            force_encoding: None,
+            interior_nul: NulHandling::Preserve,
+        }
+    }
+}
+
+///Options controlling how a document is written by
+///[`XmlDocument::to_xml_string_with`].
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    ///Number of spaces each nesting level is indented by. Defaults to 2.
+    pub indent: usize,
+    ///Write the attributes of each element sorted by name instead of in
+    ///document order, so that two documents can be diffed.
+    pub sort_attributes: bool,
+    ///Start the output with an `<?xml ...?>` declaration, so that it is a
+    ///standalone document. Defaults to `true`.
+    pub xml_declaration: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            indent: 2,
+            sort_attributes: false,
+            xml_declaration: true,
+        }
+    }
 }
 
 ///Parses an Android binary XML and returns a [`XmlDocument`] object.
@@ -58,28 +281,1429 @@ pub enum ParseError {
 ///# Ok::<(), ParseError>(())
 ///```
 pub fn parse(input: &[u8]) -> Result<XmlDocument, ParseError> {
-    let binaryxml = BinaryXmlDocument::try_from(input).map_err(ParseError::DekuError)?;
-    XmlDocument::new(binaryxml)
+    parse_with_options(input, &ParseOptions::default())
+}
+
+///Parses an Android binary XML with the given [`ParseOptions`] and returns
+///a [`XmlDocument`] object.
+///
+/// # Errors
+///
+/// Will return `ParseError` if `input` cannot be parsed
+///```rust
+///use axmldecoder::{parse_with_options, ParseOptions};
+///# use axmldecoder::ParseError;
+///let data= include_bytes!("../examples/AndroidManifest.xml");
+///let options = ParseOptions {
+///    lossy: true,
+///    ..ParseOptions::default()
+///};
+///parse_with_options(data, &options)?;
+///# Ok::<(), ParseError>(())
+///```
+pub fn parse_with_options(input: &[u8], options: &ParseOptions) -> Result<XmlDocument, ParseError> {
+    let (document, _) = XmlDocument::new(XmlEventReader::with_options(input, options)?)?;
+    Ok(document)
+}
+
+///Parses an Android binary XML like [`parse`], calling `progress` as the
+///input is read.
+///
+///`progress` is called with the number of bytes read so far and the
+///length of `input`, once after the chunks before the elements and then
+///after every chunk of the elements.
+///
+/// # Errors
+///
+/// Will return `ParseError` if `input` cannot be parsed
+///```rust
+///use axmldecoder::parse_with_progress;
+///# use axmldecoder::ParseError;
+///let data = include_bytes!("../examples/AndroidManifest.xml");
+///let mut done = 0;
+///parse_with_progress(data, |read, total| done = read * 100 / total)?;
+///assert_eq!(done, 100);
+///# Ok::<(), ParseError>(())
+///```
+pub fn parse_with_progress<'a, F>(input: &'a [u8], progress: F) -> Result<XmlDocument, ParseError>
+where
+    F: FnMut(usize, usize) + 'a,
+{
+    let mut reader = XmlEventReader::new(input)?;
+    reader.set_progress(input.len(), progress);
+    let (document, _) = XmlDocument::new(reader)?;
+    Ok(document)
+}
+
+///Reads the package details, permissions and components of an
+///`AndroidManifest.xml` without building a [`XmlDocument`].
+///
+///Elements are decoded one at a time and everything that is not part of
+///the [`ManifestSummary`] is dropped as soon as it is read.
+///
+/// # Errors
+///
+/// Will return `ParseError` if `input` cannot be parsed
+///```rust
+///use axmldecoder::scan_manifest;
+///# use axmldecoder::ParseError;
+///let data = include_bytes!("../examples/AndroidManifest.xml");
+///let summary = scan_manifest(data)?;
+///assert_eq!(summary.activities, ["TCActivity"]);
+///# Ok::<(), ParseError>(())
+///```
+pub fn scan_manifest(input: &[u8]) -> Result<ManifestSummary, ParseError> {
+    ManifestSummary::scan(XmlEventReader::new(input)?)
+}
+
+///Parses an Android binary XML and passes its contents to `handler` as
+///they are reached, without building a [`XmlDocument`].
+///
+///Only the string pool and the element being decoded are held in memory,
+///so this suits very large documents.
+///
+/// # Errors
+///
+/// Will return `ParseError` if `input` cannot be parsed. The handler has
+/// already received everything before the error.
+///```rust
+///use axmldecoder::{parse_with_handler, Element, XmlHandler};
+///# use axmldecoder::ParseError;
+///struct Count(usize);
+///
+///impl XmlHandler for Count {
+///    fn start_element(&mut self, _element: &Element) {
+///        self.0 += 1;
+///    }
+///}
+///
+///let data = include_bytes!("../examples/AndroidManifest.xml");
+///let mut count = Count(0);
+///parse_with_handler(data, &mut count)?;
+///assert_eq!(count.0, 6);
+///# Ok::<(), ParseError>(())
+///```
+pub fn parse_with_handler<H: XmlHandler>(input: &[u8], handler: &mut H) -> Result<(), ParseError> {
+    for event in XmlEventReader::new(input)? {
+        match event? {
+            XmlEvent::StartElement(e) => handler.start_element(&e),
+            XmlEvent::EndElement { tag, .. } => handler.end_element(&tag),
+            XmlEvent::Cdata(e) => handler.characters(&e),
+            XmlEvent::Comment(e) => handler.comment(&e),
+        }
+    }
+    Ok(())
+}
+
+///Parses every Android binary XML document in `input`, for files that
+///hold several documents back to back.
+///
+///Each document starts where the previous one ends according to its
+///header. Zero bytes after the last document are ignored.
+///
+/// # Errors
+///
+/// Will return `ParseError` if any of the documents cannot be parsed. The
+/// offset of the error is from the start of `input`.
+///```rust
+///use axmldecoder::parse_all;
+///# use axmldecoder::ParseError;
+///let data = include_bytes!("../examples/AndroidManifest.xml");
+///let documents = parse_all(&[&data[..], &data[..]].concat())?;
+///assert_eq!(documents.len(), 2);
+///# Ok::<(), ParseError>(())
+///```
+pub fn parse_all(input: &[u8]) -> Result<Vec<XmlDocument>, ParseError> {
+    let mut documents = Vec::new();
+    let mut offset = 0;
+    loop {
+        let rest = &input[offset..];
+        documents.push(parse(rest).map_err(binaryxml::offset_by(offset))?);
+
+        // A document that parsed has a header, and fits in the input.
+        offset += binaryxml::document_size(rest).ok_or(ParseError::UnexpectedEof)?;
+        if input[offset..].iter().all(|b| *b == 0) {
+            return Ok(documents);
+        }
+    }
+}
+
+///Parses an Android binary XML as far as possible, returning the
+///[`XmlDocument`] along with a [`Diagnostic`] for every problem that was
+///skipped over.
+///
+///Strings that cannot be decoded are replaced with empty strings, and
+///unknown chunks or elements that cannot be decoded are left out of the
+///document. If the data ends early, the elements read so far are returned
+///and [`XmlDocument::is_truncated`] is set.
+///
+/// # Errors
+///
+/// Will return `ParseError` if the document header or string pool cannot
+/// be parsed
+///```rust
+///use axmldecoder::parse_with_diagnostics;
+///# use axmldecoder::ParseError;
+///let data = include_bytes!("../examples/AndroidManifest.xml");
+///let (_, diagnostics) = parse_with_diagnostics(data)?;
+///assert!(diagnostics.is_empty());
+///# Ok::<(), ParseError>(())
+///```
+pub fn parse_with_diagnostics(input: &[u8]) -> Result<(XmlDocument, Vec<Diagnostic>), ParseError> {
+    parse_with_diagnostics_and_options(input, &ParseOptions::default())
+}
+
+///Parses an Android binary XML as far as possible like
+///[`parse_with_diagnostics`], configured by [`ParseOptions`].
+///
+/// # Errors
+///
+/// Will return `ParseError` if the document header or string pool cannot
+/// be parsed
+///```rust
+///use axmldecoder::{parse_with_diagnostics_and_options, ParseOptions};
+///# use axmldecoder::ParseError;
+///let data = include_bytes!("../examples/AndroidManifest.xml");
+///let options = ParseOptions {
+///    max_depth: 2,
+///    ..ParseOptions::default()
+///};
+///let (xml, diagnostics) = parse_with_diagnostics_and_options(data, &options)?;
+///assert_eq!(diagnostics.len(), 1);
+///assert!(xml.select("manifest/application/activity").is_empty());
+///# Ok::<(), ParseError>(())
+///```
+pub fn parse_with_diagnostics_and_options(
+    input: &[u8],
+    options: &ParseOptions,
+) -> Result<(XmlDocument, Vec<Diagnostic>), ParseError> {
+    XmlDocument::new(XmlEventReader::with_diagnostics(input, options)?)
+}
+
+///Parses a string pool chunk found anywhere, such as the global string
+///pool of a `resources.arsc` file.
+///
+///`input` has to start at the chunk header. Data after the chunk is
+///ignored. The pool only borrows `input` while it is parsed, so several
+///pools can be parsed from different threads at once.
+///
+/// # Errors
+///
+/// Will return `ParseError` if `input` does not start with a string pool
+/// or it cannot be parsed
+///```rust
+///use axmldecoder::parse_string_pool;
+///# use axmldecoder::ParseError;
+///let data = include_bytes!("../examples/AndroidManifest.xml");
+///let string_pool = parse_string_pool(&data[8..])?;
+///assert_eq!(string_pool.get(0).as_deref().map(String::as_str), Some("versionCode"));
+///# Ok::<(), ParseError>(())
+///```
+pub fn parse_string_pool(input: &[u8]) -> Result<StringPool, ParseError> {
+    let (_, string_pool) =
+        StringPool::read(input, &ParseOptions::default()).map_err(binaryxml::at(0))?;
+    Ok(string_pool)
+}
+
+///Returns every string in the first string pool of an Android binary XML
+///or `resources.arsc` file, without parsing any elements.
+///
+/// # Errors
+///
+/// Will return `ParseError` if the chunks before the string pool or the
+/// string pool itself cannot be parsed, or `input` has no string pool
+///```rust
+///use axmldecoder::extract_strings;
+///# use axmldecoder::ParseError;
+///let data = include_bytes!("../examples/AndroidManifest.xml");
+///assert!(extract_strings(data)?.iter().any(|s| s == "org.t0t0.androguard.TC"));
+///# Ok::<(), ParseError>(())
+///```
+pub fn extract_strings(input: &[u8]) -> Result<Vec<String>, ParseError> {
+    for chunk in chunks(input) {
+        let chunk = chunk?;
+        if chunk.get_type() != binaryxml::ResourceType::StringPool as u16 {
+            continue;
+        }
+
+        let offset = usize::try_from(chunk.get_offset()).map_err(|_| ParseError::Overflow)?;
+        let (_, string_pool) = StringPool::read(&input[offset..], &ParseOptions::default())
+            .map_err(binaryxml::at(offset))?;
+        return Ok(string_pool.get_strings().map(String::from).collect());
+    }
+
+    Err(ParseError::UnexpectedEof)
+}
+
+///Runs every parsing stage over `data` and discards the results.
+///
+///This is the entry point for fuzzing, for example with `cargo fuzz`. It
+///must not panic, whatever `data` contains.
+///```rust
+///axmldecoder::fuzz_parse(b"not a binary XML document");
+///```
+#[doc(hidden)]
+pub fn fuzz_parse(data: &[u8]) {
+    if let Ok(xml) = parse(data) {
+        let _ = xml.to_xml_string();
+        let _ = ManifestInfo::from_document(&xml);
+    }
+    let _ = parse_with_diagnostics(data);
+    let _ = scan_manifest(data);
+    if let Ok(reader) = XmlEventReader::new(data) {
+        reader.for_each(drop);
+    }
+    chunks(data).for_each(drop);
+    let _ = parse_string_pool(data);
+    let _ = extract_strings(data);
+}
+
+///Finds `AndroidManifest.xml` in an APK and parses it.
+///
+///Requires the `zip` feature.
+///
+/// # Errors
+///
+/// Will return `ParseError` if `apk` is not a valid zip archive, does not
+/// contain `AndroidManifest.xml`, or the manifest cannot be parsed
+#[cfg(feature = "zip")]
+pub fn parse_apk_manifest<R: std::io::Read + std::io::Seek>(
+    apk: &mut R,
+) -> Result<XmlDocument, ParseError> {
+    use std::io::Read;
+
+    let mut archive = zip::ZipArchive::new(apk).map_err(ParseError::Zip)?;
+    let mut entry = archive
+        .by_name("AndroidManifest.xml")
+        .map_err(ParseError::Zip)?;
+
+    let mut buf = Vec::new();
+    entry.read_to_end(&mut buf).map_err(ParseError::Io)?;
+    parse(&buf)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs::File;
-    use std::io::Read;
     use std::path::PathBuf;
 
+    /// Every fixture in `examples/`, with the path it was read from.
+    fn examples() -> Vec<(PathBuf, Vec<u8>)> {
+        let mut dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        dir.push("examples");
+        std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| {
+                let path = entry.unwrap().path();
+                let data = std::fs::read(&path).unwrap();
+                (path, data)
+            })
+            .collect()
+    }
+
+    /// The root element of a parsed fixture.
+    fn root_of(xml: &XmlDocument) -> &Element {
+        let Some(Node::Element(root)) = xml.get_root() else {
+            panic!("missing root element");
+        };
+        root
+    }
+
+    /// Reproducible noise from a 32-bit xorshift generator.
+    fn xorshift(mut state: u32) -> impl Iterator<Item = u32> {
+        std::iter::repeat_with(move || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state
+        })
+    }
+
     #[test]
     fn test_parse() {
-        let mut examples = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-        examples.push("examples");
+        for (path, data) in examples() {
+            parse(&data).unwrap_or_else(|_| panic!("{} failed to parse", path.display()));
+        }
+    }
+
+    #[test]
+    fn test_parser_table() {
+        // `parse` is not generic, so it can be kept behind a trait object.
+        type Parser = Box<dyn Fn(&[u8]) -> Result<XmlDocument, ParseError>>;
+        let parsers: Vec<(&str, Parser)> = vec![("axml", Box::new(parse))];
+
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let xml = (parsers[0].1)(data).unwrap();
+        assert!(xml.get_root().is_some());
+    }
+
+    #[test]
+    fn test_clone_shares_strings() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let xml = parse(data).unwrap();
+        let package =
+            |xml: &XmlDocument| match root_of(xml).get_attribute("package").unwrap().get_value() {
+                AttributeValue::String(s) => s.clone(),
+                _ => panic!("package is not a string"),
+            };
+
+        let original = package(&xml);
+        let count = std::sync::Arc::strong_count(&original);
+        let copy = xml.clone();
+        // Every reference held by the document is shared with the copy.
+        assert_eq!(std::sync::Arc::strong_count(&original), 2 * count - 1);
+        assert!(std::sync::Arc::ptr_eq(&original, &package(&copy)));
+        assert_eq!(copy.get_root(), xml.get_root());
+    }
+
+    #[test]
+    fn test_shared_tags() {
+        let data = include_bytes!("../examples/AndroidManifestWithComment.xml");
+        let xml = parse(data).unwrap();
+
+        let permissions: Vec<&Element> = xml.elements_named("uses-permission").collect();
+        assert_eq!(permissions.len(), 17);
+        let tag = permissions[0].get_shared_tag();
+        assert!(permissions
+            .iter()
+            .all(|e| std::sync::Arc::ptr_eq(e.get_shared_tag(), tag)));
+
+        let index = xml.get_string_index("uses-permission").unwrap();
+        let pooled = xml.string_pool.get(index).unwrap();
+        assert!(std::sync::Arc::ptr_eq(&pooled, tag));
+    }
+
+    #[test]
+    fn test_send_document() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let xml = parse(data).unwrap();
+
+        let tag = std::thread::spawn(move || match xml.get_root() {
+            Some(Node::Element(e)) => e.get_tag().to_string(),
+            _ => String::new(),
+        })
+        .join()
+        .unwrap();
+        assert_eq!(tag, "manifest");
+    }
+
+    #[test]
+    fn test_get_strings() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let xml = parse(data).unwrap();
+
+        let strings: Vec<&str> = xml.get_strings().collect();
+        assert_eq!(strings.len(), 21);
+        assert_eq!(xml.get_string_count(), 21);
+        assert!(strings.contains(&"manifest"));
+        assert!(strings.contains(&"http://schemas.android.com/apk/res/android"));
+    }
+
+    #[test]
+    fn test_string_encoding() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let xml = parse(data).unwrap();
+        assert_eq!(xml.get_string_encoding(), StringEncoding::Utf16);
+
+        let data = include_bytes!("../examples/AndroidManifestUTF8Strings.xml");
+        let xml = parse(data).unwrap();
+        assert_eq!(xml.get_string_encoding(), StringEncoding::Utf8);
+    }
+
+    #[test]
+    fn test_typed_attribute_values() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let xml = parse(data).unwrap();
 
-        for entry in std::fs::read_dir(examples).unwrap() {
-            let entry = entry.unwrap();
-            let mut f = File::open(entry.path()).unwrap();
-            let mut buf = Vec::new();
-            f.read_to_end(&mut buf).unwrap();
-            parse(&buf).unwrap_or_else(|_| panic!("{} failed to parse", entry.path().display()));
+        let manifest = root_of(&xml);
+        let value = |e: &Element, name: &str| {
+            e.get_typed_attributes()
+                .iter()
+                .find(|a| a.get_name() == name)
+                .unwrap()
+                .get_value()
+                .clone()
+        };
+
+        assert_eq!(
+            value(manifest, "android:versionCode"),
+            AttributeValue::Integer(1)
+        );
+        assert_eq!(
+            value(manifest, "package").to_string(),
+            "org.t0t0.androguard.TC"
+        );
+
+        let Node::Element(application) = &manifest.get_children()[0] else {
+            panic!("missing application element");
+        };
+        assert_eq!(
+            value(application, "android:debuggable"),
+            AttributeValue::Boolean(true)
+        );
+        assert_eq!(
+            value(application, "android:icon"),
+            AttributeValue::Reference(0x7f02_0000)
+        );
+    }
+
+    #[test]
+    fn test_raw_attribute_values() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let xml = parse(data).unwrap();
+
+        let manifest = root_of(&xml);
+
+        let version_code = manifest.get_attribute("android:versionCode").unwrap();
+        assert_eq!(version_code.get_value(), &AttributeValue::Integer(1));
+        assert_eq!(version_code.get_raw_type(), 0x10);
+        assert_eq!(version_code.get_raw_data(), 1);
+
+        let package = manifest.get_attribute("package").unwrap();
+        assert_eq!(package.get_raw_type(), 0x03);
+        assert_eq!(
+            Some(usize::try_from(package.get_raw_data()).unwrap()),
+            xml.get_string_index("org.t0t0.androguard.TC")
+        );
+
+        let data = unknown_value_type();
+        let xml = parse(&data).unwrap();
+        let manifest = xml.select("manifest")[0];
+        let version_code = manifest.get_attribute("android:versionCode").unwrap();
+        assert_eq!(version_code.get_raw_type(), 0x07);
+        assert_eq!(version_code.get_raw_data(), 1);
+    }
+
+    /// Returns the test manifest with the type byte of `versionCode` set
+    /// to `TYPE_DYNAMIC_REFERENCE`, which is not decoded.
+    fn unknown_value_type() -> Vec<u8> {
+        let mut data = include_bytes!("../examples/AndroidManifest.xml").to_vec();
+        let xml = parse(&data).unwrap();
+        let manifest = xml.select("manifest")[0];
+        let range = manifest
+            .get_attribute("android:versionCode")
+            .unwrap()
+            .get_byte_range();
+        data[range.end - 5] = 0x07;
+        data
+    }
+
+    #[test]
+    fn test_unknown_value_type() {
+        let data = unknown_value_type();
+        let xml = parse(&data).unwrap();
+
+        let manifest = xml.select("manifest")[0];
+        let version_code = manifest.get_attribute("android:versionCode").unwrap();
+        let value = AttributeValue::Raw {
+            data_type: 0x07,
+            data: 1,
+        };
+        assert_eq!(version_code.get_value(), &value);
+
+        #[cfg(feature = "std")]
+        {
+            let mut encoded = Vec::new();
+            xml.encode(&mut encoded).unwrap();
+            let reparsed = parse(&encoded).unwrap();
+            let manifest = reparsed.select("manifest")[0];
+            let version_code = manifest.get_attribute("android:versionCode").unwrap();
+            assert_eq!(version_code.get_value(), &value);
+        }
+    }
+
+    #[test]
+    fn test_attribute_parts() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let xml = parse(data).unwrap();
+        let manifest = root_of(&xml);
+
+        let version_code = manifest.get_attribute("android:versionCode").unwrap();
+        assert_eq!(
+            version_code.get_namespace(),
+            Some("http://schemas.android.com/apk/res/android")
+        );
+        assert_eq!(version_code.get_local_name(), "versionCode");
+        assert_eq!(version_code.get_raw_value(), None);
+        assert_eq!(version_code.get_value(), &AttributeValue::Integer(1));
+
+        let package = manifest.get_attribute("package").unwrap();
+        assert_eq!(package.get_namespace(), None);
+        assert_eq!(package.get_local_name(), "package");
+        assert_eq!(package.get_raw_value(), Some("org.t0t0.androguard.TC"));
+        assert_eq!(package.get_value().to_string(), "org.t0t0.androguard.TC");
+
+        // Both a raw string and a typed integer.
+        let data = include_bytes!("../examples/AndroidManifestTextChunksXML.xml");
+        let xml = parse(data).unwrap();
+        let manifest = root_of(&xml);
+        let version = manifest.get_attribute("platformBuildVersionCode").unwrap();
+        assert_eq!(version.get_namespace(), None);
+        assert_eq!(version.get_local_name(), "platformBuildVersionCode");
+        assert_eq!(version.get_raw_value(), Some("25"));
+        assert_eq!(version.get_value(), &AttributeValue::Integer(25));
+
+        #[cfg(feature = "std")]
+        {
+            let mut encoded = Vec::new();
+            xml.encode(&mut encoded).unwrap();
+            let reparsed = parse(&encoded).unwrap();
+            let manifest = root_of(&reparsed);
+            let version = manifest.get_attribute("platformBuildVersionCode").unwrap();
+            assert_eq!(version.get_raw_value(), Some("25"));
+        }
+    }
+
+    #[test]
+    fn test_byte_ranges() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let xml = parse(data).unwrap();
+
+        let manifest = root_of(&xml);
+        let element = manifest.get_byte_range();
+        assert_eq!(
+            u16::from_le_bytes([data[element.start], data[element.start + 1]]),
+            chunk_type::RES_XML_START_ELEMENT_TYPE
+        );
+        let size = u32::from_le_bytes(
+            data[element.start + 4..element.start + 8]
+                .try_into()
+                .unwrap(),
+        );
+        assert_eq!(element.len(), usize::try_from(size).unwrap());
+
+        let application = manifest.find("application").unwrap();
+        let debuggable = application.get_attribute("android:debuggable").unwrap();
+        let range = debuggable.get_byte_range();
+        assert_eq!(range.len(), 20);
+        assert!(application.get_byte_range().contains(&range.start));
+        assert_eq!(data[range.end - 5], debuggable.get_raw_type());
+        assert_eq!(
+            data[range.end - 4..range.end],
+            debuggable.get_raw_data().to_le_bytes()
+        );
+
+        let mut patched = data.to_vec();
+        patched[range.end - 4..range.end].copy_from_slice(&0u32.to_le_bytes());
+        let xml = parse(&patched).unwrap();
+        let manifest = root_of(&xml);
+        let debuggable = manifest
+            .find("application")
+            .and_then(|e| e.get_attribute("android:debuggable"))
+            .unwrap();
+        assert_eq!(debuggable.get_value(), &AttributeValue::Boolean(false));
+    }
+
+    #[test]
+    fn test_attribute_layout() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let xml = parse(data).unwrap();
+        let application = xml.select("manifest/application")[0];
+        let count = application.get_typed_attributes().len();
+
+        // Rewrite <application> with 4 unused bytes before the attributes
+        // and after each of them.
+        let range = application.get_byte_range();
+        let chunk = &data[range.clone()];
+        let mut patched = chunk[..36].to_vec();
+        patched[24..28].copy_from_slice(&[24, 0, 24, 0]);
+        patched.extend_from_slice(&[0xff; 4]);
+        for attribute in chunk[36..].chunks(20) {
+            patched.extend_from_slice(attribute);
+            patched.extend_from_slice(&[0xff; 4]);
+        }
+        let size = u32::try_from(patched.len()).unwrap();
+        patched[4..8].copy_from_slice(&size.to_le_bytes());
+        let mut document = data[..range.start].to_vec();
+        document.extend_from_slice(&patched);
+        document.extend_from_slice(&data[range.end..]);
+        let size = u32::try_from(document.len()).unwrap();
+        document[4..8].copy_from_slice(&size.to_le_bytes());
+
+        let patched = parse(&document).unwrap();
+        let patched_application = patched.select("manifest/application")[0];
+        assert_eq!(
+            patched_application.get_attributes(),
+            application.get_attributes()
+        );
+        assert_eq!(patched_application.get_typed_attributes().len(), count);
+        for (i, attribute) in patched_application
+            .get_typed_attributes()
+            .iter()
+            .enumerate()
+        {
+            let range = attribute.get_byte_range();
+            assert_eq!(
+                range.start,
+                patched_application.get_byte_range().start + 40 + i * 24
+            );
+            assert_eq!(
+                document[range.end - 4..range.end],
+                attribute.get_raw_data().to_le_bytes()
+            );
+        }
+    }
+
+    #[test]
+    fn test_comments() {
+        let data = include_bytes!("../examples/AndroidManifestWithComment.xml");
+        let xml = parse(data).unwrap();
+
+        let comments = XmlEventReader::new(data)
+            .unwrap()
+            .filter(|e| matches!(e, Ok(XmlEvent::Comment(_))))
+            .count();
+        assert_eq!(comments, 1);
+
+        let manifest = root_of(&xml);
+        let Some(Node::Element(application)) = manifest
+            .get_children()
+            .iter()
+            .find(|n| matches!(n, Node::Element(e) if e.get_tag() == "application"))
+        else {
+            panic!("missing application element");
+        };
+        let children = application.get_children();
+        let i = children
+            .iter()
+            .position(|n| matches!(n, Node::Comment(_)))
+            .unwrap();
+        let Node::Comment(comment) = &children[i] else {
+            unreachable!()
+        };
+        assert_eq!(comment.get_data(), "installLocation");
+        assert!(matches!(&children[i + 1], Node::Element(e) if e.get_tag() == "meta-data"));
+
+        assert!(xml.to_xml_string().contains("<!--installLocation-->\n"));
+    }
+
+    #[test]
+    fn test_get_attribute() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let xml = parse(data).unwrap();
+        let root = root_of(&xml);
+
+        let version_code = root.get_attribute("android:versionCode").unwrap();
+        assert_eq!(version_code.get_value(), &AttributeValue::Integer(1));
+        assert_eq!(
+            root.get_attribute_ns(
+                Some("http://schemas.android.com/apk/res/android"),
+                "versionCode"
+            ),
+            Some(version_code)
+        );
+        assert!(root.get_attribute("versionCode").is_none());
+        assert!(root.get_attribute_ns(None, "versionCode").is_none());
+
+        let package = root.get_attribute("package").unwrap();
+        assert_eq!(root.get_attribute_ns(None, "package"), Some(package));
+        assert!(root.get_attribute("android:package").is_none());
+
+        let map = root.get_attribute_map();
+        assert_eq!(map.len(), 3);
+        assert_eq!(map["android:versionCode"], version_code);
+        assert_eq!(map["package"], package);
+    }
+
+    #[test]
+    fn test_into_tree() {
+        let data = include_bytes!("../examples/AndroidManifestWithComment.xml");
+        let Some(Node::Element(root)) = parse(data).unwrap().into_tree() else {
+            panic!("missing root element");
+        };
+
+        assert_eq!(root.children_named("uses-permission").count(), 17);
+        assert_eq!(root.children_named("activity").count(), 0);
+
+        let activity = root.find("application/activity").unwrap();
+        assert_eq!(activity.get_tag(), "activity");
+        assert!(root.find("application/missing").is_none());
+        assert!(root.find("").is_none());
+    }
+
+    #[test]
+    fn test_namespace_scope() {
+        let data = include_bytes!("../examples/AndroidManifestDoubleNamespace.xml");
+        let xml = parse(data).unwrap();
+
+        let manifest = root_of(&xml);
+        let Some(Node::Element(application)) = manifest
+            .get_children()
+            .iter()
+            .find(|n| matches!(n, Node::Element(e) if e.get_tag() == "application"))
+        else {
+            panic!("missing application element");
+        };
+        let activities: Vec<&Element> = application
+            .get_children()
+            .iter()
+            .filter_map(|n| match n {
+                Node::Element(e) if e.get_tag() == "activity" => Some(e),
+                _ => None,
+            })
+            .collect();
+
+        let android = "http://schemas.android.com/apk/res/android";
+        let i = activities
+            .iter()
+            .position(|e| e.get_namespace_uri("andorid").is_some())
+            .unwrap();
+        assert_eq!(activities[i].get_namespace_uri("andorid"), Some(android));
+        assert_eq!(activities[i].get_namespace_uri("android"), Some(android));
+        assert_eq!(
+            activities[i]
+                .get_namespace_declarations()
+                .collect::<Vec<_>>(),
+            [("andorid", android)]
+        );
+        assert!(activities[i]
+            .get_attributes()
+            .contains_key("andorid:exported"));
+
+        // The declaration goes out of scope with the element.
+        assert_eq!(activities[i + 1].get_namespace_uri("andorid"), None);
+        assert!(activities[i + 1]
+            .get_attributes()
+            .keys()
+            .all(|k| k.starts_with("android:")));
+    }
+
+    #[test]
+    fn test_line_numbers() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let xml = parse(data).unwrap();
+
+        let manifest = root_of(&xml);
+        assert_eq!(manifest.get_line_number(), 2);
+        assert_eq!(manifest.get_end_line_number(), 15);
+
+        let Node::Element(application) = &manifest.get_children()[0] else {
+            panic!("missing application element");
+        };
+        assert_eq!(application.get_line_number(), 6);
+    }
+
+    #[test]
+    fn test_get_attribute_resource_id() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let xml = parse(data).unwrap();
+
+        let index = xml.get_string_index("versionCode").unwrap();
+        assert_eq!(xml.get_attribute_resource_id(index), Some(0x0101_021b));
+
+        let index = xml.get_string_index("manifest").unwrap();
+        assert_eq!(xml.get_attribute_resource_id(index), None);
+    }
+
+    /// Parses `data` and returns the error along with the offset it was
+    /// reported at.
+    fn parse_error_at(data: &[u8]) -> (u64, ParseError) {
+        match parse(data) {
+            Err(ParseError::At { offset, source }) => (offset, *source),
+            other => panic!("expected an error with an offset, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_not_binary_xml() {
+        let mut data = include_bytes!("../examples/AndroidManifest.xml").to_vec();
+        data[0] = 0x02;
+        assert!(matches!(parse(&data), Err(ParseError::NotBinaryXml)));
+
+        let text = b"<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<manifest package=\"a.b\"/>\n";
+        assert!(matches!(parse(text), Err(ParseError::NotBinaryXml)));
+
+        let random: Vec<u8> = xorshift(0x2545_f491)
+            .map(|x| x.to_le_bytes()[0])
+            .take(1024)
+            .collect();
+        assert!(matches!(parse(&random), Err(ParseError::NotBinaryXml)));
+
+        assert!(matches!(parse(&[]), Err(ParseError::NotBinaryXml)));
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        assert!(matches!(parse(&data[..4]), Err(ParseError::NotBinaryXml)));
+    }
+
+    #[test]
+    fn test_unexpected_chunk() {
+        let mut data = include_bytes!("../examples/AndroidManifest.xml").to_vec();
+        data[8] = 0x03;
+        assert!(matches!(
+            parse_error_at(&data),
+            (
+                8,
+                ParseError::UnexpectedChunk {
+                    expected: 0x0001,
+                    found: 0x0003
+                }
+            )
+        ));
+    }
+
+    #[test]
+    fn test_string_count_out_of_bounds() {
+        let mut data = include_bytes!("../examples/AndroidManifest.xml").to_vec();
+        data[16..20].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            parse_error_at(&data),
+            (8, ParseError::StringCountOutOfBounds(u32::MAX))
+        ));
+    }
+
+    #[test]
+    fn test_error_display() {
+        let mut data = include_bytes!("../examples/AndroidManifest.xml").to_vec();
+        data[8] = 0x03;
+        let e: Box<dyn std::error::Error> = Box::new(parse(&data).unwrap_err());
+        assert_eq!(
+            e.to_string(),
+            "expected chunk type 0x0001, found 0x0003 at offset 8"
+        );
+        assert!(e.source().is_some());
+
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        assert_eq!(
+            parse(&data[..data.len() - 10]).unwrap_err().to_string(),
+            format!(
+                "parse error: Not enough data, need 32 bits (or 4 bytes) at offset {}",
+                data.len() - 24
+            )
+        );
+    }
+
+    #[test]
+    fn test_error_offset() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+
+        for cut in [data.len() - 10, data.len() - 100, data.len() - 300] {
+            let (offset, _) = parse_error_at(&data[..cut]);
+            let offset = usize::try_from(offset).unwrap();
+            assert!(offset <= cut && cut - offset < 100, "{offset} {cut}");
+        }
+    }
+
+    #[test]
+    fn test_diagnostics_unknown_chunk() {
+        let mut data = include_bytes!("../examples/AndroidManifest.xml").to_vec();
+        let chunk_size =
+            |offset: usize| u32::from_le_bytes(data[offset + 4..offset + 8].try_into().unwrap());
+        let resource_map = 8 + usize::try_from(chunk_size(8)).unwrap();
+        let first_node = resource_map + usize::try_from(chunk_size(resource_map)).unwrap();
+        data[first_node..first_node + 2].copy_from_slice(&0x0105_u16.to_le_bytes());
+
+        assert!(parse(&data).is_err());
+
+        let (xml, diagnostics) = parse_with_diagnostics(&data).unwrap();
+        assert!(matches!(xml.get_root(), Some(Node::Element(e)) if e.get_tag() == "manifest"));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].get_offset(), first_node as u64);
+        assert_eq!(
+            diagnostics[0].get_message(),
+            "expected chunk type 0x0102, found 0x0105"
+        );
+    }
+
+    #[test]
+    fn test_diagnostics_bad_string() {
+        let mut data = include_bytes!("../examples/AndroidManifest.xml").to_vec();
+        // Point the first string past the end of the pool.
+        data[36..40].copy_from_slice(&0x00ff_ffff_u32.to_le_bytes());
+
+        assert!(parse(&data).is_err());
+
+        let (xml, diagnostics) = parse_with_diagnostics(&data).unwrap();
+        assert_eq!(xml.get_strings().next(), Some(""));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].get_offset(), 8);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "offset 16777335 points past the end of its chunk at 700 at offset 8"
+        );
+    }
+
+    #[test]
+    fn test_truncated_document() {
+        let data = include_bytes!("../examples/AndroidManifestWithComment.xml");
+        let (xml, diagnostics) = parse_with_diagnostics(data).unwrap();
+        assert!(!xml.is_truncated());
+        assert!(diagnostics.is_empty());
+
+        let truncated = &data[..data.len() / 2];
+        assert!(parse(truncated).is_err());
+
+        let (xml, diagnostics) = parse_with_diagnostics(truncated).unwrap();
+        assert!(xml.is_truncated());
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(
+            diagnostics[1].get_message(),
+            "start and end of elements do not match"
+        );
+        let manifest = root_of(&xml);
+        assert_eq!(manifest.get_tag(), "manifest");
+        assert_eq!(manifest.children_named("uses-permission").count(), 16);
+    }
+
+    #[test]
+    fn test_parse_with_progress() {
+        let data = include_bytes!("../examples/AndroidManifestWithComment.xml");
+        let mut calls = Vec::new();
+        let xml = parse_with_progress(data, |read, total| calls.push((read, total))).unwrap();
+        assert_eq!(xml.get_root(), parse(data).unwrap().get_root());
+
+        // The header, string pool and resource map are reported together.
+        let nodes: usize = xml.get_chunk_stats().values().sum::<usize>() - 3;
+        assert_eq!(calls.len(), nodes + 1);
+        assert!(calls.windows(2).all(|w| w[0].0 < w[1].0));
+        assert!(calls.iter().all(|(_, total)| *total == data.len()));
+        assert_eq!(calls.last(), Some(&(data.len(), data.len())));
+    }
+
+    #[test]
+    fn test_framework_resources() {
+        // Some attributes here are only named through the resource map.
+        let data = include_bytes!("../examples/AndroidManifest_NamespaceInAttributeName.xml");
+        let xml = parse(data).unwrap();
+        let manifest = root_of(&xml);
+        assert!(manifest.get_attribute("versionName").is_some());
+
+        let mut options = ParseOptions::default();
+        options
+            .framework_resources
+            .insert(0x0101_021c, "renamedVersionName");
+        let xml = parse_with_options(data, &options).unwrap();
+        let manifest = root_of(&xml);
+        assert!(manifest.get_attribute("versionName").is_none());
+        assert_eq!(
+            manifest
+                .get_attribute("renamedVersionName")
+                .unwrap()
+                .get_value()
+                .to_string(),
+            "1.0"
+        );
+    }
+
+    #[test]
+    fn test_trailing_data() {
+        let original = include_bytes!("../examples/AndroidManifest.xml");
+        let mut data = original.to_vec();
+        data.extend(xorshift(0x1234_5678).map(|x| x.to_le_bytes()[0]).take(64));
+
+        let xml = parse(&data).unwrap();
+        assert_eq!(xml.get_root(), parse(original).unwrap().get_root());
+
+        let options = ParseOptions {
+            reject_trailing_data: true,
+            ..ParseOptions::default()
+        };
+        assert!(parse_with_options(original, &options).is_ok());
+        let Err(ParseError::At { offset, source }) = parse_with_options(&data, &options) else {
+            panic!("trailing data was accepted");
+        };
+        assert_eq!(offset, original.len() as u64);
+        assert!(matches!(*source, ParseError::TrailingData(64)));
+    }
+
+    #[test]
+    fn test_chunk_stats() {
+        use chunk_type::*;
+
+        let data = include_bytes!("../examples/AndroidManifestWithComment.xml");
+        let xml = parse(data).unwrap();
+        let stats: Vec<_> = xml
+            .get_chunk_stats()
+            .iter()
+            .map(|(typ, count)| (*typ, *count))
+            .collect();
+        assert_eq!(
+            stats,
+            [
+                (RES_STRING_POOL_TYPE, 1),
+                (RES_XML_TYPE, 1),
+                (RES_XML_START_NAMESPACE_TYPE, 1),
+                (RES_XML_END_NAMESPACE_TYPE, 1),
+                (RES_XML_START_ELEMENT_TYPE, 77),
+                (RES_XML_END_ELEMENT_TYPE, 77),
+                (RES_XML_RESOURCE_MAP_TYPE, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_padded_chunks() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let expected = parse(data).unwrap();
+        let set_size = |data: &mut [u8], offset: usize, size: usize| {
+            let size = u32::try_from(size).unwrap();
+            data[offset + 4..offset + 8].copy_from_slice(&size.to_le_bytes());
+        };
+
+        // Pad the final end of namespace with 4 bytes after its fields.
+        let last = chunks(data).map(Result::unwrap).last().unwrap();
+        let offset = usize::try_from(last.get_offset()).unwrap();
+        let mut padded = data.to_vec();
+        padded.extend_from_slice(&[0; 4]);
+        set_size(&mut padded, offset, 28);
+        let len = padded.len();
+        set_size(&mut padded, 0, len);
+        assert_eq!(parse(&padded).unwrap().get_root(), expected.get_root());
+
+        // Give <application> a larger header, which moves its extension.
+        let range = expected.select("manifest/application")[0].get_byte_range();
+        let mut padded = data[..range.start + 16].to_vec();
+        padded.extend_from_slice(&[0xff; 4]);
+        padded.extend_from_slice(&data[range.start + 16..]);
+        padded[range.start + 2..range.start + 4].copy_from_slice(&20_u16.to_le_bytes());
+        set_size(&mut padded, range.start, range.len() + 4);
+        let len = padded.len();
+        set_size(&mut padded, 0, len);
+        let xml = parse(&padded).unwrap();
+        assert_eq!(xml.get_root(), expected.get_root());
+        let debuggable = xml.select("manifest/application")[0]
+            .get_attribute("android:debuggable")
+            .unwrap();
+        let range = debuggable.get_byte_range();
+        assert_eq!(
+            padded[range.end - 4..range.end],
+            debuggable.get_raw_data().to_le_bytes()
+        );
+    }
+
+    #[test]
+    fn test_null_chunks() {
+        let original = include_bytes!("../examples/AndroidManifest.xml");
+        let chunk_size = |offset: usize| {
+            u32::from_le_bytes(original[offset + 4..offset + 8].try_into().unwrap())
+        };
+        let resource_map = 8 + usize::try_from(chunk_size(8)).unwrap();
+        let first_node = resource_map + usize::try_from(chunk_size(resource_map)).unwrap();
+
+        let null_chunk = |size: u32| {
+            let mut chunk = vec![0; 8];
+            chunk[2..4].copy_from_slice(&8_u16.to_le_bytes());
+            chunk[4..8].copy_from_slice(&size.to_le_bytes());
+            chunk.resize(usize::try_from(size).unwrap().max(8), 0);
+            chunk
+        };
+        let with_chunks = |first: &[u8], second: &[u8]| {
+            let mut data = [
+                &original[..resource_map],
+                first,
+                &original[resource_map..first_node],
+                second,
+                &original[first_node..],
+            ]
+            .concat();
+            let size = u32::try_from(data.len()).unwrap();
+            data[4..8].copy_from_slice(&size.to_le_bytes());
+            data
+        };
+
+        let data = with_chunks(&null_chunk(8), &null_chunk(16));
+        let xml = parse(&data).unwrap();
+        assert_eq!(xml.get_root(), parse(original).unwrap().get_root());
+        assert_eq!(xml.get_chunk_stats()[&chunk_type::RES_NULL_TYPE], 2);
+
+        let data = with_chunks(&[], &null_chunk(0));
+        let Err(ParseError::At { offset, source }) = parse(&data) else {
+            panic!("empty null chunk was accepted");
+        };
+        assert_eq!(offset, first_node as u64);
+        assert!(matches!(*source, ParseError::ZeroSizeChunk));
+    }
+
+    #[test]
+    fn test_parse_all() {
+        let first = include_bytes!("../examples/AndroidManifest.xml");
+        let second = include_bytes!("../examples/AndroidManifestWithComment.xml");
+        let data = [&first[..], &second[..], &[0; 4]].concat();
+
+        let documents = parse_all(&data).unwrap();
+        assert_eq!(documents.len(), 2);
+        assert_eq!(documents[0].get_root(), parse(first).unwrap().get_root());
+        assert_eq!(documents[1].get_root(), parse(second).unwrap().get_root());
+
+        let mut data = data;
+        data.push(1);
+        assert!(matches!(
+            parse_all(&data),
+            Err(ParseError::At { offset, source })
+                if offset == u64::try_from(first.len() + second.len()).unwrap()
+                    && matches!(*source, ParseError::NotBinaryXml)
+        ));
+
+        assert!(parse_all(&[]).is_err());
+    }
+
+    #[test]
+    fn test_parse_string_pool() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let xml = parse(data).unwrap();
+        let string_pool = parse_string_pool(&data[8..]).unwrap();
+
+        assert!(string_pool.get_strings().eq(xml.get_strings()));
+        assert_eq!(string_pool.get_encoding(), xml.get_string_encoding());
+
+        assert!(matches!(
+            parse_string_pool(data),
+            Err(ParseError::At { offset: 0, source })
+                if matches!(*source, ParseError::UnexpectedChunk { expected: 0x0001, found: 0x0003 })
+        ));
+    }
+
+    #[test]
+    fn test_parse_string_pools_concurrently() {
+        let first = include_bytes!("../examples/AndroidManifest.xml");
+        let second = include_bytes!("../examples/AndroidManifestWithComment.xml");
+
+        let (a, b) = std::thread::scope(|s| {
+            let a = s.spawn(|| parse_string_pool(&first[8..]));
+            let b = s.spawn(|| parse_string_pool(&second[8..]));
+            (a.join().unwrap().unwrap(), b.join().unwrap().unwrap())
+        });
+        assert!(a.get_strings().eq(parse(first).unwrap().get_strings()));
+        assert!(b.get_strings().eq(parse(second).unwrap().get_strings()));
+    }
+
+    #[test]
+    fn test_fuzz_parse() {
+        let mut random = xorshift(0x9e37_79b9);
+        let mut next = move || usize::try_from(random.next().unwrap()).unwrap();
+
+        // Only the small fixtures, to keep the test fast in debug builds.
+        let corpus: [&[u8]; 4] = [
+            include_bytes!("../examples/AndroidManifest.xml"),
+            include_bytes!("../examples/AndroidManifestUTF8Strings.xml"),
+            include_bytes!("../examples/AndroidManifestNonZeroStyle.xml"),
+            include_bytes!("../examples/AndroidManifestNullbytes.xml"),
+        ];
+        for data in corpus {
+            for cut in (0..data.len()).step_by(5) {
+                fuzz_parse(&data[..cut]);
+            }
+
+            for _ in 0..250 {
+                let mut mutated = data.to_vec();
+                for _ in 0..1 + next() % 8 {
+                    let i = next() % mutated.len();
+                    mutated[i] = u8::try_from(next() & 0xFF).unwrap();
+                }
+                fuzz_parse(&mutated);
+            }
+        }
+
+        for len in 0..256 {
+            let random: Vec<u8> = (0..len)
+                .map(|_| u8::try_from(next() & 0xFF).unwrap())
+                .collect();
+            fuzz_parse(&random);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_max_depth() {
+        let mut builder = DocumentBuilder::new();
+        for _ in 0..20 {
+            builder.start_element("e");
+        }
+        let mut data = Vec::new();
+        builder.build().unwrap().encode(&mut data).unwrap();
+
+        let options = |max_depth| ParseOptions {
+            max_depth,
+            ..ParseOptions::default()
+        };
+        assert!(parse_with_options(&data, &options(20)).is_ok());
+        assert!(matches!(
+            parse_with_options(&data, &options(19)),
+            Err(ParseError::At { source, .. }) if matches!(*source, ParseError::DepthExceeded(19))
+        ));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_duplicate_attributes() {
+        const ANDROID: &str = "http://schemas.android.com/apk/res/android";
+        let mut builder = DocumentBuilder::new();
+        builder
+            .start_element("manifest")
+            .start_element("activity")
+            .attribute(Some(ANDROID), "exported", AttributeValue::Boolean(false))
+            .attribute(
+                Some(ANDROID),
+                "name",
+                AttributeValue::String(".Main".to_string().into()),
+            )
+            .attribute(Some(ANDROID), "exported", AttributeValue::Boolean(true))
+            .end_element()
+            .end_element();
+        let mut data = Vec::new();
+        builder.build().unwrap().encode(&mut data).unwrap();
+
+        assert!(parse(&data).is_ok());
+
+        let options = ParseOptions {
+            reject_duplicate_attributes: true,
+            ..ParseOptions::default()
+        };
+        assert!(matches!(
+            parse_with_options(&data, &options),
+            Err(ParseError::At { source, .. })
+                if matches!(&*source, ParseError::DuplicateAttribute(name) if name == "android:exported")
+        ));
+
+        let (xml, diagnostics) = parse_with_diagnostics(&data).unwrap();
+        assert!(xml.get_root().is_some());
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].get_message(),
+            "attribute android:exported is set more than once"
+        );
+    }
+
+    #[test]
+    fn test_extract_strings() {
+        for (_, data) in examples() {
+            let xml = parse(&data).unwrap();
+            assert!(extract_strings(&data).unwrap().iter().eq(xml.get_strings()));
         }
+
+        assert!(matches!(
+            extract_strings(&[0x03, 0x00, 0x08, 0x00, 0x08, 0x00, 0x00, 0x00]),
+            Err(ParseError::UnexpectedEof)
+        ));
+    }
+
+    #[test]
+    fn test_chunks() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let chunks: Vec<RawChunkHeader> = chunks(data).collect::<Result<_, _>>().unwrap();
+
+        let types: Vec<u16> = chunks.iter().map(RawChunkHeader::get_type).collect();
+        assert_eq!(
+            types,
+            [
+                0x0003, 0x0001, 0x0180, 0x0100, 0x0102, 0x0102, 0x0102, 0x0102, 0x0102, 0x0103,
+                0x0102, 0x0103, 0x0103, 0x0103, 0x0103, 0x0103, 0x0101
+            ]
+        );
+
+        assert_eq!(chunks[0].get_size() as usize, data.len());
+        assert_eq!(chunks[1].get_offset(), 8);
+        assert_eq!(chunks[1].get_header_size(), 28);
+        assert_eq!(chunks[2].get_offset(), u64::from(8 + chunks[1].get_size()));
+    }
+
+    #[test]
+    fn test_chunks_invalid_size() {
+        let mut data = include_bytes!("../examples/AndroidManifest.xml").to_vec();
+        data[12..16].copy_from_slice(&4_u32.to_le_bytes());
+
+        let results: Vec<_> = chunks(&data).collect();
+        assert_eq!(results.len(), 2);
+        assert!(matches!(
+            &results[1],
+            Err(ParseError::At { offset: 8, source })
+                if matches!(**source, ParseError::InvalidChunkSize(4))
+        ));
+    }
+
+    #[test]
+    fn test_zero_size_chunks() {
+        let original = include_bytes!("../examples/AndroidManifest.xml");
+        let chunk_size = |offset: usize| {
+            usize::try_from(u32::from_le_bytes(
+                original[offset + 4..offset + 8].try_into().unwrap(),
+            ))
+            .unwrap()
+        };
+        let resource_map = 8 + chunk_size(8);
+        let first_node = resource_map + chunk_size(resource_map);
+
+        for (offset, typ) in [
+            (8, None),
+            (first_node, None),
+            (first_node, Some(0x0204_u16)),
+        ] {
+            let mut data = original.to_vec();
+            data[offset + 4..offset + 8].copy_from_slice(&0_u32.to_le_bytes());
+            if let Some(typ) = typ {
+                data[offset..offset + 2].copy_from_slice(&typ.to_le_bytes());
+            }
+
+            // Every walk over the chunks stops instead of looping.
+            let results: Vec<_> = chunks(&data).collect();
+            assert!(matches!(
+                results.last(),
+                Some(Err(ParseError::At { source, .. }))
+                    if matches!(**source, ParseError::ZeroSizeChunk)
+            ));
+            assert!(parse(&data).is_err());
+            let _ = parse_with_diagnostics(&data);
+            let _ = parse_all(&data);
+        }
+    }
+
+    #[cfg(feature = "zip")]
+    #[test]
+    fn test_parse_apk_manifest() {
+        use std::io::{Cursor, Write};
+        use zip::write::SimpleFileOptions;
+        use zip::CompressionMethod;
+
+        let manifest = include_bytes!("../examples/AndroidManifest.xml");
+
+        for method in [CompressionMethod::Stored, CompressionMethod::Deflated] {
+            let mut apk = zip::ZipWriter::new(Cursor::new(Vec::new()));
+            let options = SimpleFileOptions::default().compression_method(method);
+            apk.start_file("classes.dex", options).unwrap();
+            apk.write_all(b"dex\n035\0").unwrap();
+            apk.start_file("AndroidManifest.xml", options).unwrap();
+            apk.write_all(manifest).unwrap();
+            let mut apk = apk.finish().unwrap();
+
+            let xml = parse_apk_manifest(&mut apk).unwrap();
+            assert_eq!(xml.get_root(), parse(manifest).unwrap().get_root());
+        }
+
+        let mut apk = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        apk.start_file("classes.dex", SimpleFileOptions::default())
+            .unwrap();
+        let mut apk = apk.finish().unwrap();
+        assert!(matches!(
+            parse_apk_manifest(&mut apk),
+            Err(ParseError::Zip(zip::result::ZipError::FileNotFound))
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialize_json() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let xml = parse(data).unwrap();
+
+        let expected = concat!(
+            r#"{"root":{"element":{"attributes":{"#,
+            r#""xmlns:android":"http://schemas.android.com/apk/res/android","#,
+            r#""android:versionCode":"1","android:versionName":"1.0","#,
+            r#""package":"org.t0t0.androguard.TC"},"tag":"manifest","children":[{"element":{"#,
+            r#""attributes":{"android:label":"@0x7f040000","#,
+            r#""android:icon":"@0x7f020000","#,
+            r#""android:debuggable":"true"},"tag":"application","children":["#,
+            r#"{"element":{"attributes":{"android:label":"@0x7f040000","#,
+            r#""android:name":"TCActivity"},"tag":"activity","children":[{"element":{"#,
+            r#""attributes":{},"tag":"intent-filter","children":[{"element":{"attributes":{"#,
+            r#""android:name":"android.intent.action.MAIN"},"tag":"action","children":[]}},"#,
+            r#"{"element":{"attributes":{"android:name":"android.intent.category.LAUNCHER"},"#,
+            r#""tag":"category","children":[]}}]}}]}}]}}]}}}"#,
+        );
+        assert_eq!(serde_json::to_string(&xml).unwrap(), expected);
+
+        let root = root_of(&xml);
+        assert_eq!(
+            serde_json::to_string(root.get_typed_attributes()[0].get_value()).unwrap(),
+            r#"{"Integer":1}"#
+        );
     }
 }
@@ -0,0 +1,616 @@
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::binaryxml::{
+    at, BinaryXmlDocument, ResourceMap, XmlNode, XmlNodeType, XmlNodes, ATTRIBUTE_SIZE,
+};
+use crate::diagnostic::{Diagnostic, Diagnostics};
+use crate::resources::FrameworkResources;
+use crate::stringpool::{to_usize, StringPool};
+use crate::xml::{Cdata, Comment, Element, XmlDocument};
+use crate::{ParseError, ParseOptions};
+
+///Enum representing the events produced by [`XmlEventReader`].
+#[derive(Debug)]
+pub enum XmlEvent {
+    ///The start of an element. The element has no children, those are
+    ///reported by the events that follow.
+    StartElement(Element),
+    ///The end of the most recently started element.
+    EndElement { tag: Arc<String>, line_number: u32 },
+    ///Text content of the current element.
+    Cdata(Cdata),
+    ///A comment, reported right before the node it is attached to.
+    Comment(Comment),
+}
+
+///Trait for receiving the contents of a document from
+///[`parse_with_handler`](crate::parse_with_handler) as it is parsed.
+///
+///Every method does nothing by default, so a handler only has to
+///implement the ones it needs.
+pub trait XmlHandler {
+    ///Called at the start of an element, before any of its children.
+    fn start_element(&mut self, _element: &Element) {}
+
+    ///Called at the end of the element with the tag `tag`.
+    fn end_element(&mut self, _tag: &str) {}
+
+    ///Called with text content of the current element.
+    fn characters(&mut self, _cdata: &Cdata) {}
+
+    ///Called with a comment, right before the node it is attached to.
+    fn comment(&mut self, _comment: &Comment) {}
+}
+
+///Pull parser that walks the chunks of an Android binary XML and yields
+///[`XmlEvent`]s one at a time.
+///
+///The string pool is read up front but elements are only decoded as they
+///are reached, so callers can stop early without parsing the rest of the
+///document.
+///```rust
+///use axmldecoder::{XmlEvent, XmlEventReader};
+///# use axmldecoder::ParseError;
+///let data = include_bytes!("../examples/AndroidManifest.xml");
+///for event in XmlEventReader::new(data)? {
+///    if let XmlEvent::StartElement(e) = event? {
+///        if e.get_tag() == "application" {
+///            break;
+///        }
+///    }
+///}
+///# Ok::<(), ParseError>(())
+///```
+#[derive(Debug)]
+pub struct XmlEventReader<'a> {
+    string_pool: StringPool,
+    resource_map: ResourceMap,
+    nodes: XmlNodes<'a>,
+    // Stack of `(uri, prefix)` pairs for the namespaces currently in
+    // scope, with the innermost declaration last.
+    namespaces: Vec<(Arc<String>, Arc<String>)>,
+    pending_namespaces: Vec<(Arc<String>, Arc<String>)>,
+    // Event held back while the comment attached to its node is returned.
+    pending_event: Option<XmlEvent>,
+    diagnostics: Diagnostics,
+    // Depth of the element being skipped after it failed to decode.
+    skip_depth: usize,
+    // Tags of the elements currently open, with the innermost last.
+    open: Vec<Arc<String>>,
+    max_depth: usize,
+    reject_duplicate_attributes: bool,
+    framework_resources: FrameworkResources,
+    progress: Option<Progress<'a>>,
+    // Tags of the elements whose attributes are decoded, or `None` for all.
+    attribute_filter: Option<fn(&str) -> bool>,
+    // Set once an error is returned, after which the iterator is done.
+    failed: bool,
+}
+
+/// Callback told how many bytes of the input have been read, along with
+/// the length of the input.
+struct Progress<'a> {
+    callback: Box<dyn FnMut(usize, usize) + 'a>,
+    total: usize,
+}
+
+impl fmt::Debug for Progress<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Progress")
+            .field("total", &self.total)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a> XmlEventReader<'a> {
+    ///Creates a reader over an Android binary XML.
+    ///
+    /// # Errors
+    ///
+    /// Will return `ParseError` if the document header or string pool of
+    /// `input` cannot be parsed
+    pub fn new(input: &'a [u8]) -> Result<Self, ParseError> {
+        Self::with_options(input, &ParseOptions::default())
+    }
+
+    ///Creates a reader over an Android binary XML with the given
+    ///[`ParseOptions`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `ParseError` if the document header or string pool of
+    /// `input` cannot be parsed
+    pub fn with_options(input: &'a [u8], options: &ParseOptions) -> Result<Self, ParseError> {
+        Self::open(input, options, Diagnostics::default())
+    }
+
+    /// Creates a reader that records recoverable errors instead of
+    /// returning them.
+    pub(crate) fn with_diagnostics(
+        input: &'a [u8],
+        options: &ParseOptions,
+    ) -> Result<Self, ParseError> {
+        Self::open(input, options, Diagnostics::collecting())
+    }
+
+    fn open(
+        input: &'a [u8],
+        options: &ParseOptions,
+        mut diagnostics: Diagnostics,
+    ) -> Result<Self, ParseError> {
+        let binaryxml = BinaryXmlDocument::read(input, options, &mut diagnostics)?;
+
+        // There are some files without the XmlStartNameSpace element.
+        // We should assume that the android namespace is always present even
+        // if not explicitly defined in the document.
+        //
+        // examples/AndroidManifestNoNamespace.xml
+        let namespaces = vec![(
+            Arc::new("http://schemas.android.com/apk/res/android".to_string()),
+            Arc::new("android".to_string()),
+        )];
+
+        Ok(Self {
+            string_pool: binaryxml.string_pool,
+            resource_map: binaryxml.resource_map,
+            nodes: binaryxml.elements,
+            namespaces,
+            pending_namespaces: Vec::new(),
+            pending_event: None,
+            diagnostics,
+            skip_depth: 0,
+            open: Vec::new(),
+            max_depth: options.max_depth,
+            reject_duplicate_attributes: options.reject_duplicate_attributes,
+            framework_resources: options.framework_resources.clone(),
+            progress: None,
+            attribute_filter: None,
+            failed: false,
+        })
+    }
+
+    /// Calls `callback` with the number of bytes read so far and `total`
+    /// once for the chunks before the nodes, and then after every node
+    /// chunk.
+    pub(crate) fn set_progress(&mut self, total: usize, callback: impl FnMut(usize, usize) + 'a) {
+        let mut progress = Progress {
+            callback: Box::new(callback),
+            total,
+        };
+        (progress.callback)(self.nodes.position(), total);
+        self.progress = Some(progress);
+    }
+
+    /// Only decodes the attributes of elements whose tag `keep` accepts.
+    /// Other elements are returned with just their tag, which saves
+    /// decoding values and names that would be dropped.
+    pub(crate) fn set_attribute_filter(&mut self, keep: fn(&str) -> bool) {
+        self.attribute_filter = Some(keep);
+    }
+
+    /// Returns whether the input ends before the size given in its
+    /// document header.
+    pub(crate) fn is_truncated(&self) -> bool {
+        self.nodes.truncated
+    }
+
+    pub(crate) fn into_parts(
+        self,
+    ) -> (
+        StringPool,
+        ResourceMap,
+        BTreeMap<u16, usize>,
+        FrameworkResources,
+        Vec<Diagnostic>,
+    ) {
+        (
+            self.string_pool,
+            self.resource_map,
+            self.nodes.chunk_counts,
+            self.framework_resources,
+            self.diagnostics.into_vec(),
+        )
+    }
+
+    fn next_event(&mut self) -> Result<Option<XmlEvent>, ParseError> {
+        if let Some(event) = self.pending_event.take() {
+            return Ok(Some(event));
+        }
+
+        while let Some(node) = self.nodes.next() {
+            if let Some(progress) = &mut self.progress {
+                (progress.callback)(self.nodes.position(), progress.total);
+            }
+            let (offset, node) = match node {
+                Ok(node) => node,
+                Err(e) => {
+                    self.diagnostics.recover(e)?;
+                    continue;
+                }
+            };
+
+            let is_start = matches!(node.element, XmlNodeType::XmlStartElement(_));
+            if self.skip_depth > 0 {
+                match node.element {
+                    XmlNodeType::XmlStartElement(_) => self.skip_depth += 1,
+                    XmlNodeType::XmlEndElement(_) => self.skip_depth -= 1,
+                    _ => {}
+                }
+                continue;
+            }
+
+            match self.process_node(offset, node) {
+                Ok(Some(event)) => return Ok(Some(event)),
+                Ok(None) => {}
+                Err(e) => {
+                    self.diagnostics.recover(at(offset)(e))?;
+                    // Drop the children of an element that failed to
+                    // decode so the start and end events stay balanced.
+                    if is_start {
+                        self.skip_depth = 1;
+                    }
+                }
+            }
+        }
+
+        if !self.open.is_empty() {
+            self.open.clear();
+            let e = at(self.nodes.position())(ParseError::UnbalancedElements);
+            self.diagnostics.recover(e)?;
+        }
+        Ok(None)
+    }
+
+    fn process_node(
+        &mut self,
+        offset: usize,
+        node: XmlNode,
+    ) -> Result<Option<XmlEvent>, ParseError> {
+        let line_number = node.header.line_no;
+        let comment = node.header.comment;
+        let header_size = usize::from(node.header.chunk_header.header_size);
+        let size = to_usize(node.header.chunk_header.size)?;
+        let byte_range = offset..offset.saturating_add(size);
+        let event = match node.element {
+            XmlNodeType::XmlStartNameSpace(e) => {
+                let namespace = XmlDocument::process_namespace(e.uri, e.prefix, &self.string_pool)?;
+                self.namespaces.push(namespace.clone());
+                self.pending_namespaces.push(namespace);
+                return Ok(None);
+            }
+            XmlNodeType::XmlEndNameSpace(e) => {
+                let namespace = XmlDocument::process_namespace(e.uri, e.prefix, &self.string_pool)?;
+                if let Some(i) = self.namespaces.iter().rposition(|n| *n == namespace) {
+                    self.namespaces.remove(i);
+                }
+                return Ok(None);
+            }
+            XmlNodeType::XmlStartElement(e) => {
+                if self.open.len() >= self.max_depth {
+                    return Err(ParseError::DepthExceeded(self.max_depth));
+                }
+                let tag = self
+                    .string_pool
+                    .get(to_usize(e.attr_ext.name)?)
+                    .ok_or(ParseError::StringNotFound(e.attr_ext.name))?;
+                let mut element = match self.attribute_filter {
+                    Some(keep) if !keep(&tag) => Element::new(tag),
+                    _ => XmlDocument::process_start_element(
+                        &e,
+                        &self.string_pool,
+                        &self.namespaces,
+                        &self.resource_map,
+                        &self.framework_resources,
+                    )?,
+                };
+                element.namespace_declarations = core::mem::take(&mut self.pending_namespaces);
+                element.line_number = line_number;
+                let start = offset + header_size + usize::from(e.attr_ext.attribute_start);
+                let size = usize::from(e.attr_ext.attribute_size);
+                for (i, attribute) in element.typed_attributes.iter_mut().enumerate() {
+                    // Only the attribute's own fields belong to it, not any
+                    // bytes that follow them.
+                    let attribute_start = start + i * size;
+                    attribute.byte_range =
+                        attribute_start..attribute_start + usize::from(ATTRIBUTE_SIZE);
+                }
+                element.byte_range = byte_range;
+                if let Some(name) = duplicate_attribute(&element) {
+                    let e = at(offset)(ParseError::DuplicateAttribute(name.to_string()));
+                    if self.reject_duplicate_attributes {
+                        self.diagnostics.recover(e)?;
+                    } else {
+                        self.diagnostics.warn(e);
+                    }
+                }
+                self.open.push(element.get_shared_tag().clone());
+                XmlEvent::StartElement(element)
+            }
+            XmlNodeType::XmlEndElement(e) => {
+                let open = self.open.pop().ok_or(ParseError::UnbalancedElements)?;
+                // The end still has to be reported to close the element.
+                let tag = match self.string_pool.get(to_usize(e.name)?) {
+                    Some(tag) if tag != open => {
+                        self.diagnostics
+                            .recover(at(offset)(ParseError::UnbalancedElements))?;
+                        open
+                    }
+                    Some(tag) => tag,
+                    None => {
+                        self.diagnostics
+                            .recover(at(offset)(ParseError::StringNotFound(e.name)))?;
+                        open
+                    }
+                };
+                // The build tools repeat the comment of the start element
+                // here, so it is not reported again.
+                return Ok(Some(XmlEvent::EndElement { tag, line_number }));
+            }
+            XmlNodeType::XmlCdata(e) => {
+                let mut cdata = XmlDocument::process_cdata(&e, &self.string_pool)?;
+                cdata.line_number = line_number;
+                cdata.byte_range = byte_range;
+                XmlEvent::Cdata(cdata)
+            }
+        };
+
+        if comment == u32::MAX {
+            return Ok(Some(event));
+        }
+
+        let Some(data) = self.string_pool.get(to_usize(comment)?) else {
+            self.diagnostics
+                .recover(at(offset)(ParseError::StringNotFound(comment)))?;
+            return Ok(Some(event));
+        };
+        self.pending_event = Some(event);
+        Ok(Some(XmlEvent::Comment(Comment { data, line_number })))
+    }
+}
+
+/// Returns the first attribute name that `e` has more than once.
+fn duplicate_attribute(e: &Element) -> Option<&str> {
+    let mut names = BTreeSet::new();
+    e.get_typed_attributes()
+        .iter()
+        .map(|a| a.get_name())
+        .find(|name| !names.insert(*name))
+}
+
+impl Iterator for XmlEventReader<'_> {
+    type Item = Result<XmlEvent, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Stop at the first error instead of resuming mid-document, which
+        // would also report the elements left open a second time.
+        if self.failed {
+            return None;
+        }
+        let event = self.next_event();
+        self.failed = event.is_err();
+        event.transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handler() {
+        #[derive(Default)]
+        struct Tags {
+            started: Vec<String>,
+            ended: Vec<String>,
+        }
+
+        impl XmlHandler for Tags {
+            fn start_element(&mut self, element: &Element) {
+                self.started.push(element.get_tag().to_string());
+            }
+
+            fn end_element(&mut self, tag: &str) {
+                self.ended.push(tag.to_string());
+            }
+        }
+
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let mut handler = Tags::default();
+        crate::parse_with_handler(data, &mut handler).unwrap();
+
+        assert_eq!(
+            handler.started,
+            [
+                "manifest",
+                "application",
+                "activity",
+                "intent-filter",
+                "action",
+                "category"
+            ]
+        );
+        assert_eq!(
+            handler.ended,
+            [
+                "action",
+                "category",
+                "intent-filter",
+                "activity",
+                "application",
+                "manifest"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_events() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+
+        let mut tags = Vec::new();
+        let mut depth = 0;
+        for event in XmlEventReader::new(data).unwrap() {
+            match event.unwrap() {
+                XmlEvent::StartElement(e) => {
+                    assert!(e.get_children().is_empty());
+                    tags.push(e.get_tag().to_string());
+                    depth += 1;
+                }
+                XmlEvent::EndElement { .. } => depth -= 1,
+                XmlEvent::Cdata(_) | XmlEvent::Comment(_) => {}
+            }
+        }
+
+        assert_eq!(depth, 0);
+        assert_eq!(
+            tags,
+            [
+                "manifest",
+                "application",
+                "activity",
+                "intent-filter",
+                "action",
+                "category"
+            ]
+        );
+    }
+
+    fn end_elements(data: &[u8]) -> Vec<(usize, usize)> {
+        crate::chunks(data)
+            .map(Result::unwrap)
+            .filter(|c| c.get_type() == crate::chunk_type::RES_XML_END_ELEMENT_TYPE)
+            .map(|c| {
+                let offset = usize::try_from(c.get_offset()).unwrap();
+                (offset, usize::try_from(c.get_size()).unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_premature_end_element() {
+        let mut data = include_bytes!("../examples/AndroidManifest.xml").to_vec();
+        let parent = crate::parse(&data)
+            .unwrap()
+            .get_string_index("intent-filter")
+            .unwrap();
+        // Close <intent-filter> in place of the <action> inside it.
+        let (offset, _) = end_elements(&data)[0];
+        data[offset + 20..offset + 24]
+            .copy_from_slice(&u32::try_from(parent).unwrap().to_le_bytes());
+
+        let Err(ParseError::At { offset: at, source }) = crate::parse(&data) else {
+            panic!("unbalanced elements were accepted");
+        };
+        assert_eq!(at, offset as u64);
+        assert!(matches!(*source, ParseError::UnbalancedElements));
+    }
+
+    #[test]
+    fn test_unclosed_element() {
+        let mut data = include_bytes!("../examples/AndroidManifest.xml").to_vec();
+        // Drop the end of <manifest> and shrink the document to match.
+        let (offset, size) = *end_elements(&data).last().unwrap();
+        data.drain(offset..offset + size);
+        let document_size = u32::try_from(data.len()).unwrap();
+        data[4..8].copy_from_slice(&document_size.to_le_bytes());
+
+        // The error points at the end of the document, where the element
+        // is still open.
+        let Err(ParseError::At { offset, source }) = crate::parse(&data) else {
+            panic!("an unclosed element was accepted");
+        };
+        assert_eq!(offset, data.len() as u64);
+        assert!(matches!(*source, ParseError::UnbalancedElements));
+
+        let error = at(data.len())(ParseError::UnbalancedElements);
+        let events: Vec<_> = XmlEventReader::new(&data)
+            .unwrap()
+            .map(|event| match event {
+                Ok(XmlEvent::StartElement(e)) => format!("<{}>", e.get_tag()),
+                Ok(XmlEvent::EndElement { tag, .. }) => format!("</{tag}>"),
+                Ok(XmlEvent::Cdata(_) | XmlEvent::Comment(_)) => "text".to_string(),
+                Err(e) => e.to_string(),
+            })
+            .collect();
+        assert_eq!(
+            events,
+            [
+                "<manifest>",
+                "<application>",
+                "<activity>",
+                "<intent-filter>",
+                "<action>",
+                "</action>",
+                "<category>",
+                "</category>",
+                "</intent-filter>",
+                "</activity>",
+                "</application>",
+                &error.to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stop_after_error() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        // Cut the document inside the end of <manifest>.
+        let (offset, _) = *end_elements(data).last().unwrap();
+        let data = &data[..offset + 10];
+
+        let events: Vec<_> = XmlEventReader::new(data).unwrap().collect();
+        let errors = events.iter().filter(|event| event.is_err()).count();
+        assert_eq!(errors, 1);
+        assert!(events.last().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_attribute_filter() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+
+        let mut reader = XmlEventReader::new(data).unwrap();
+        reader.set_attribute_filter(|tag| tag == "activity");
+        let attributes: Vec<_> = reader
+            .filter_map(|event| match event.unwrap() {
+                XmlEvent::StartElement(e) => {
+                    Some((e.get_tag().to_string(), e.get_typed_attributes().len()))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            attributes,
+            [
+                ("manifest".to_string(), 0),
+                ("application".to_string(), 0),
+                ("activity".to_string(), 2),
+                ("intent-filter".to_string(), 0),
+                ("action".to_string(), 0),
+                ("category".to_string(), 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_early_exit() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+
+        let mut reader = XmlEventReader::new(data).unwrap();
+        let application = reader.find_map(|event| match event {
+            Ok(XmlEvent::StartElement(e)) if e.get_tag() == "application" => Some(e),
+            _ => None,
+        });
+        assert!(application.is_some());
+
+        // The rest of the document is still available.
+        assert!(matches!(
+            reader.next(),
+            Some(Ok(XmlEvent::StartElement(e))) if e.get_tag() == "activity"
+        ));
+    }
+}
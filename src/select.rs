@@ -0,0 +1,528 @@
+use alloc::collections::BTreeSet;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use crate::value::AttributeValue;
+use crate::xml::{Attribute, Element, Node, XmlDocument};
+
+impl XmlDocument {
+    ///Returns the elements matching `path`, in document order.
+    ///
+    ///`path` is a list of steps separated by `/`, starting at the root
+    ///element. A step is a tag, or `*` to match any element, followed by
+    ///any number of `[@name='value']` predicates. Prefixed attribute names
+    ///are resolved through the namespaces in scope. An invalid path
+    ///matches nothing.
+    ///```rust
+    ///# use axmldecoder::ParseError;
+    ///let data = include_bytes!("../examples/AndroidManifest.xml");
+    ///let xml = axmldecoder::parse(data)?;
+    ///let activities = xml.select("manifest/application/activity[@android:name='TCActivity']");
+    ///assert_eq!(activities.len(), 1);
+    ///# Ok::<(), ParseError>(())
+    ///```
+    #[must_use]
+    pub fn select(&self, path: &str) -> Vec<&Element> {
+        let mut matches = Vec::new();
+        let (Some(Node::Element(root)), Some(steps)) = (self.get_root(), parse_path(path)) else {
+            return matches;
+        };
+
+        select_steps(root, &steps, &mut matches);
+        matches
+    }
+
+    ///Sets the attribute `name` in the namespace `namespace` to `value` on
+    ///every element matching `path`, see [`XmlDocument::select`], adding
+    ///the attribute where it is missing. Returns the number of elements
+    ///that were changed.
+    ///
+    ///Only the tree is changed. The document's string pool is left as it
+    ///was parsed and nothing records the change: a new string only enters
+    ///the string pool when the document is encoded, see
+    ///`XmlDocument::encode`. Until then the raw data of such a value is
+    ///`u32::MAX`.
+    ///```rust
+    ///use axmldecoder::AttributeValue;
+    ///# use axmldecoder::ParseError;
+    ///let data = include_bytes!("../examples/AndroidManifest.xml");
+    ///let mut xml = axmldecoder::parse(data)?;
+    ///let android = Some("http://schemas.android.com/apk/res/android");
+    ///let changed = xml.set_attribute(
+    ///    "manifest/application",
+    ///    android,
+    ///    "debuggable",
+    ///    AttributeValue::Boolean(false),
+    ///);
+    ///assert_eq!(changed, 1);
+    ///# Ok::<(), ParseError>(())
+    ///```
+    pub fn set_attribute(
+        &mut self,
+        path: &str,
+        namespace: Option<&str>,
+        name: &str,
+        value: AttributeValue,
+    ) -> usize {
+        let (Some(Node::Element(root)), Some(steps)) = (self.root.as_mut(), parse_path(path))
+        else {
+            return 0;
+        };
+
+        let string = match &value {
+            AttributeValue::String(s) => self.string_pool.index_of(s),
+            _ => None,
+        };
+        let string = string
+            .and_then(|i| u32::try_from(i).ok())
+            .unwrap_or(u32::MAX);
+
+        let mut changed = 0;
+        set_attribute_steps(root, &steps, &mut |e| {
+            e.set_attribute(
+                namespace,
+                name,
+                value.clone(),
+                string,
+                &self.framework_resources,
+            );
+            changed += 1;
+        });
+        changed
+    }
+
+    ///Returns an iterator over every element with the tag `tag`, at any
+    ///depth, in document order.
+    ///```rust
+    ///# use axmldecoder::ParseError;
+    ///let data = include_bytes!("../examples/AndroidManifest.xml");
+    ///let xml = axmldecoder::parse(data)?;
+    ///assert_eq!(xml.elements_named("activity").count(), 1);
+    ///# Ok::<(), ParseError>(())
+    ///```
+    pub fn elements_named<'a: 'b, 'b>(
+        &'a self,
+        tag: &'b str,
+    ) -> impl Iterator<Item = &'a Element> + 'b {
+        self.elements().filter(move |e| e.get_tag() == tag)
+    }
+
+    ///Returns the first element, in document order, for which `predicate`
+    ///returns `true`. The rest of the document is not visited once it is
+    ///found.
+    ///```rust
+    ///# use axmldecoder::ParseError;
+    ///let data = include_bytes!("../examples/AndroidManifest.xml");
+    ///let xml = axmldecoder::parse(data)?;
+    ///let application = xml.find_element(|e| e.get_tag() == "application");
+    ///assert!(application.is_some());
+    ///# Ok::<(), ParseError>(())
+    ///```
+    pub fn find_element<P>(&self, mut predicate: P) -> Option<&Element>
+    where
+        P: FnMut(&Element) -> bool,
+    {
+        self.elements().find(|e| predicate(e))
+    }
+
+    /// Returns an iterator over every element in document order.
+    fn elements(&self) -> impl Iterator<Item = &Element> {
+        let mut stack = Vec::new();
+        if let Some(Node::Element(root)) = self.get_root() {
+            stack.push(root);
+        }
+
+        core::iter::from_fn(move || {
+            let e = stack.pop()?;
+            stack.extend(
+                e.get_children()
+                    .iter()
+                    .rev()
+                    .filter_map(|child| match child {
+                        Node::Element(child) => Some(child),
+                        _ => None,
+                    }),
+            );
+            Some(e)
+        })
+    }
+
+    ///Returns the distinct qualified names of the attributes used on any
+    ///element of the document.
+    ///```rust
+    ///# use axmldecoder::ParseError;
+    ///let data = include_bytes!("../examples/AndroidManifest.xml");
+    ///let xml = axmldecoder::parse(data)?;
+    ///assert!(xml.attribute_names().contains("android:debuggable"));
+    ///# Ok::<(), ParseError>(())
+    ///```
+    #[must_use]
+    pub fn attribute_names(&self) -> BTreeSet<&str> {
+        let mut names = BTreeSet::new();
+        if let Some(Node::Element(root)) = self.get_root() {
+            collect_attribute_names(root, &mut names);
+        }
+        names
+    }
+}
+
+fn collect_attribute_names<'a>(e: &'a Element, names: &mut BTreeSet<&'a str>) {
+    names.extend(e.get_typed_attributes().iter().map(Attribute::get_name));
+    for child in e.get_children() {
+        if let Node::Element(child) = child {
+            collect_attribute_names(child, names);
+        }
+    }
+}
+
+struct Step<'a> {
+    tag: &'a str,
+    predicates: Vec<(&'a str, &'a str)>,
+}
+
+impl Step<'_> {
+    fn matches(&self, e: &Element) -> bool {
+        (self.tag == "*" || self.tag == e.get_tag())
+            && self.predicates.iter().all(|(name, value)| {
+                find_attribute(e, name).is_some_and(|a| a.get_value().to_string() == *value)
+            })
+    }
+}
+
+fn select_steps<'a>(e: &'a Element, steps: &[Step], matches: &mut Vec<&'a Element>) {
+    let Some((step, rest)) = steps.split_first() else {
+        return;
+    };
+    if !step.matches(e) {
+        return;
+    }
+    if rest.is_empty() {
+        matches.push(e);
+        return;
+    }
+
+    for child in e.get_children() {
+        if let Node::Element(child) = child {
+            select_steps(child, rest, matches);
+        }
+    }
+}
+
+fn set_attribute_steps(e: &mut Element, steps: &[Step], set: &mut impl FnMut(&mut Element)) {
+    let Some((step, rest)) = steps.split_first() else {
+        return;
+    };
+    if !step.matches(e) {
+        return;
+    }
+    if rest.is_empty() {
+        set(e);
+        return;
+    }
+
+    for child in &mut e.children {
+        if let Node::Element(child) = child {
+            set_attribute_steps(child, rest, set);
+        }
+    }
+}
+
+fn find_attribute<'a>(e: &'a Element, name: &str) -> Option<&'a Attribute> {
+    let attribute = match name.split_once(':') {
+        Some((prefix, local_name)) => e
+            .get_namespace_uri(prefix)
+            .and_then(|uri| e.get_attribute_ns(Some(uri), local_name)),
+        None => e.get_attribute_ns(None, name),
+    };
+    // Attributes named through the resource map only have a qualified
+    // name.
+    attribute.or_else(|| e.get_attribute(name))
+}
+
+fn parse_path(path: &str) -> Option<Vec<Step<'_>>> {
+    let mut steps = Vec::new();
+    let mut rest = path.strip_prefix('/').unwrap_or(path);
+    loop {
+        let (step, after) = parse_step(rest)?;
+        steps.push(step);
+        match after.strip_prefix('/') {
+            Some(after) => rest = after,
+            None if after.is_empty() => return Some(steps),
+            None => return None,
+        }
+    }
+}
+
+/// Parses one step, returning it along with the rest of the path.
+fn parse_step(input: &str) -> Option<(Step<'_>, &str)> {
+    let end = input.find(['/', '[']).unwrap_or(input.len());
+    let (tag, mut rest) = input.split_at(end);
+    if tag.is_empty() {
+        return None;
+    }
+
+    let mut predicates = Vec::new();
+    while let Some(predicate) = rest.strip_prefix("[@") {
+        let (name, predicate) = predicate.split_once('=')?;
+        let quote = predicate
+            .chars()
+            .next()
+            .filter(|c| *c == '\'' || *c == '"')?;
+        let (value, predicate) = predicate[1..].split_once(quote)?;
+        rest = predicate.strip_prefix(']')?;
+        predicates.push((name, value));
+    }
+
+    Some((Step { tag, predicates }, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::binaryxml::ResourceValueType;
+    use crate::manifest::ANDROID_NAMESPACE;
+    use crate::value::AttributeValue;
+    use crate::{parse, ParseOptions};
+
+    #[test]
+    fn test_select() {
+        let data = include_bytes!("../examples/AndroidManifestWithComment.xml");
+        let xml = parse(data).unwrap();
+
+        let activities = xml.select("manifest/application/activity");
+        assert_eq!(activities.len(), 3);
+        assert!(activities.iter().all(|e| e.get_tag() == "activity"));
+        assert_eq!(xml.select("/manifest/*/activity").len(), 3);
+        assert_eq!(xml.select("manifest/uses-permission").len(), 17);
+        assert!(xml.select("application/activity").is_empty());
+    }
+
+    #[test]
+    fn test_elements_named() {
+        let data = include_bytes!("../examples/AndroidManifestWithComment.xml");
+        let xml = parse(data).unwrap();
+
+        assert_eq!(xml.elements_named("activity").count(), 3);
+        assert_eq!(xml.elements_named("uses-permission").count(), 17);
+        assert_eq!(xml.elements_named("manifest").count(), 1);
+        assert_eq!(xml.elements_named("missing").count(), 0);
+
+        let names: Vec<_> = xml
+            .elements_named("activity")
+            .filter_map(|e| e.get_attribute("android:name"))
+            .map(|a| a.get_value().to_string())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "com.phone2.stop.activity.MainActivity",
+                "com.phone2.stop.activity.DeleteActivity",
+                "com.phone2.stop.activity.DefaultSmsActivity"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_element() {
+        let data = include_bytes!("../examples/AndroidManifestWithComment.xml");
+        let xml = parse(data).unwrap();
+
+        let application = xml.find_element(|e| e.get_tag() == "application").unwrap();
+        assert!(application.children_named("activity").count() > 0);
+
+        let mut visited = 0;
+        let activity = xml
+            .find_element(|e| {
+                visited += 1;
+                e.get_tag() == "activity"
+            })
+            .unwrap();
+        assert_eq!(
+            activity
+                .get_attribute("android:name")
+                .unwrap()
+                .get_value()
+                .to_string(),
+            "com.phone2.stop.activity.MainActivity"
+        );
+        // The document has 77 elements, the search stops at the first
+        // match.
+        assert!(visited < 77);
+        assert!(xml.find_element(|e| e.get_tag() == "missing").is_none());
+    }
+
+    #[test]
+    fn test_attribute_names() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let xml = parse(data).unwrap();
+
+        assert_eq!(
+            xml.attribute_names().into_iter().collect::<Vec<_>>(),
+            [
+                "android:debuggable",
+                "android:icon",
+                "android:label",
+                "android:name",
+                "android:versionCode",
+                "android:versionName",
+                "package"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_set_attribute() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let mut xml = parse(data).unwrap();
+
+        let android = Some(ANDROID_NAMESPACE);
+        assert_eq!(
+            xml.set_attribute(
+                "manifest/application",
+                android,
+                "debuggable",
+                AttributeValue::Boolean(false)
+            ),
+            1
+        );
+        assert_eq!(
+            xml.set_attribute(
+                "manifest",
+                android,
+                "versionCode",
+                AttributeValue::Integer(42)
+            ),
+            1
+        );
+        assert_eq!(
+            xml.set_attribute(
+                "manifest/application",
+                android,
+                "allowBackup",
+                AttributeValue::Boolean(false)
+            ),
+            1
+        );
+        assert_eq!(
+            xml.set_attribute("manifest/missing", None, "a", AttributeValue::Integer(1)),
+            0
+        );
+
+        let application = xml.select("manifest/application")[0];
+        let allow_backup = application.get_attribute("android:allowBackup").unwrap();
+        assert_eq!(allow_backup.get_value(), &AttributeValue::Boolean(false));
+        assert_eq!(allow_backup.resource_id, Some(0x0101_0280));
+        assert_eq!(
+            allow_backup.get_raw_type(),
+            ResourceValueType::Boolean as u8
+        );
+        assert_eq!(application.get_attributes()["android:debuggable"], "false");
+
+        let manifest = xml.select("manifest")[0];
+        let version_code = manifest.get_attribute("android:versionCode").unwrap();
+        assert_eq!(version_code.get_raw_type(), ResourceValueType::Dec as u8);
+        assert_eq!(version_code.get_raw_data(), 42);
+        assert_eq!(manifest.get_attributes()["android:versionCode"], "42");
+    }
+
+    #[test]
+    fn test_set_attribute_framework_resources() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let mut options = ParseOptions::default();
+        options
+            .framework_resources
+            .insert(0x0101_0fff, "futureAttribute");
+        let mut xml = crate::parse_with_options(data, &options).unwrap();
+
+        xml.set_attribute(
+            "manifest",
+            Some(ANDROID_NAMESPACE),
+            "futureAttribute",
+            AttributeValue::Boolean(true),
+        );
+        let manifest = xml.select("manifest")[0];
+        let attribute = manifest.get_attribute("android:futureAttribute").unwrap();
+        assert_eq!(attribute.resource_id, Some(0x0101_0fff));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_set_attribute_round_trip() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let mut xml = parse(data).unwrap();
+
+        let android = Some(ANDROID_NAMESPACE);
+        let name = AttributeValue::String(std::sync::Arc::new("Renamed".to_string()));
+        xml.set_attribute("manifest/application", android, "label", name.clone());
+        xml.set_attribute(
+            "manifest",
+            android,
+            "versionCode",
+            AttributeValue::Integer(42),
+        );
+        xml.set_attribute(
+            "manifest/application",
+            android,
+            "allowBackup",
+            AttributeValue::Boolean(false),
+        );
+        let label = xml.select("manifest/application")[0]
+            .get_attribute("android:label")
+            .unwrap();
+        assert_eq!(label.get_raw_data(), u32::MAX);
+        assert!(!xml.get_strings().any(|s| s == "Renamed"));
+
+        let mut encoded = Vec::new();
+        xml.encode(&mut encoded).unwrap();
+        let xml = parse(&encoded).unwrap();
+
+        let manifest = xml.select("manifest")[0];
+        assert_eq!(
+            manifest
+                .get_attribute("android:versionCode")
+                .unwrap()
+                .get_value(),
+            &AttributeValue::Integer(42)
+        );
+        let application = xml.select("manifest/application")[0];
+        assert_eq!(
+            application
+                .get_attribute("android:label")
+                .unwrap()
+                .get_value(),
+            &name
+        );
+        let allow_backup = application.get_attribute("android:allowBackup").unwrap();
+        assert_eq!(allow_backup.get_value(), &AttributeValue::Boolean(false));
+        assert_eq!(allow_backup.resource_id, Some(0x0101_0280));
+        assert!(xml.get_strings().any(|s| s == "Renamed"));
+    }
+
+    #[test]
+    fn test_select_predicate() {
+        let data = include_bytes!("../examples/AndroidManifestWithComment.xml");
+        let xml = parse(data).unwrap();
+
+        let activities = xml.select(
+            "manifest/application/activity[@android:name='com.phone2.stop.activity.DeleteActivity']",
+        );
+        assert_eq!(activities.len(), 1);
+        assert_eq!(
+            activities[0]
+                .get_attribute("android:excludeFromRecents")
+                .unwrap()
+                .get_value()
+                .to_string(),
+            "false"
+        );
+
+        let services = xml.select(
+            "manifest/application/service[@android:process=\":thee_second2_service3\"][@android:name='com.phone.stop6.service.SmsService']",
+        );
+        assert_eq!(services.len(), 1);
+        assert!(xml
+            .select("manifest/application/*[@android:name='missing']")
+            .is_empty());
+        assert!(xml.select("manifest[@package]").is_empty());
+        assert!(xml.select("").is_empty());
+    }
+}
@@ -1,15 +1,24 @@
-use deku::bitvec::{BitSlice, Msb0};
 use deku::prelude::*;
 
-use byteorder::ByteOrder;
-use byteorder::LittleEndian;
-use std::io::Read;
-use std::rc::Rc;
+use alloc::collections::BTreeSet;
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use deku::ctx::Endian;
 
-use crate::binaryxml::ChunkHeader;
-use crate::ParseError;
+use crate::binaryxml::{
+    at, expect_chunk_type, read_chunk, read_u16, read_u32, take, ChunkHeader, ResourceType,
+};
+use crate::diagnostic::Diagnostics;
+use crate::{ParseError, ParseOptions};
 
-#[derive(Debug, DekuRead, DekuWrite)]
+#[derive(Debug, Clone, DekuRead, DekuWrite)]
+#[deku(
+    endian = "endian",
+    ctx = "endian: Endian",
+    ctx_default = "Endian::Little"
+)]
 pub(crate) struct StringPoolHeader {
     pub(crate) chunk_header: ChunkHeader,
     pub(crate) string_count: u32,
@@ -19,127 +28,1337 @@ pub(crate) struct StringPoolHeader {
     pub(crate) style_start: u32,
 }
 
-#[derive(Debug, DekuRead)]
-pub(crate) struct StringPool {
+///Struct representing a string pool, as used by binary XML documents and
+///`resources.arsc` files.
+#[derive(Debug, Clone)]
+pub struct StringPool {
     pub(crate) header: StringPoolHeader,
-    #[deku(reader = "StringPool::read_strings(header, deku::rest)")]
-    pub(crate) strings: Vec<Rc<String>>,
+    pub(crate) encoding: StringEncoding,
+    pub(crate) strings: Vec<Arc<String>>,
+    pub(crate) styles: Vec<Vec<StyleSpan>>,
+    pub(crate) string_data: Vec<u8>,
+    // The parts of the chunk that are only kept to write it back
+    // unchanged: the string and style offsets as read, the style data and
+    // the byte order. Only the encoder reads them.
+    #[cfg(feature = "std")]
+    pub(crate) offsets: Vec<u32>,
+    #[cfg(feature = "std")]
+    pub(crate) style_data: Vec<u8>,
+    #[cfg(feature = "std")]
+    pub(crate) endian: Endian,
 }
 
-type DekuRest = BitSlice<u8, Msb0>;
+///Enum representing the encoding of the strings in a string pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StringEncoding {
+    Utf8,
+    Utf16,
+}
+
+///Enum representing how NUL characters inside the strings of a string pool
+///are handled. Obfuscated files use them to cut strings short for tools
+///that treat strings as C strings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NulHandling {
+    ///Keep the NUL characters in the string.
+    #[default]
+    Preserve,
+    ///Remove the NUL characters from the string.
+    Strip,
+    ///Fail with [`ParseError::InteriorNul`].
+    Reject,
+}
+
+impl NulHandling {
+    fn apply(self, s: String) -> Result<String, ParseError> {
+        if !s.contains('\0') {
+            return Ok(s);
+        }
+        match self {
+            NulHandling::Preserve => Ok(s),
+            NulHandling::Strip => Ok(s.replace('\0', "")),
+            NulHandling::Reject => Err(ParseError::InteriorNul),
+        }
+    }
+}
+
+impl StringEncoding {
+    ///Returns the encoding given by the `flags` of a string pool header.
+    ///Strings are UTF-8 if bit 8 (`0x100`) is set and UTF-16 otherwise.
+    #[must_use]
+    pub fn from_flags(flags: u32) -> Self {
+        if flags & UTF8_FLAG != 0 {
+            StringEncoding::Utf8
+        } else {
+            StringEncoding::Utf16
+        }
+    }
+}
+
+/// String pool flag marking the strings as sorted, so they can be
+/// binary searched.
+pub(crate) const SORTED_FLAG: u32 = 1 << 0;
+/// String pool flag marking the strings as UTF-8 instead of UTF-16.
+pub(crate) const UTF8_FLAG: u32 = 1 << 8;
+
+/// Every string pool flag that has a meaning.
+const KNOWN_FLAGS: u32 = SORTED_FLAG | UTF8_FLAG;
+
+/// A style span as stored in the pool, named after `ResStringPool_span`.
+#[derive(Debug, PartialEq, Eq, DekuRead, DekuWrite)]
+#[deku(
+    endian = "endian",
+    ctx = "endian: Endian",
+    ctx_default = "Endian::Little"
+)]
+pub(crate) struct ResStringPoolSpan {
+    pub(crate) name: u32,
+    pub(crate) first_char: u32,
+    pub(crate) last_char: u32,
+}
+
+///A style applied to a range of characters within a string, such as the
+///`b` in `<b>bold</b> text`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StyleSpan {
+    name: Arc<String>,
+    first_char: u32,
+    last_char: u32,
+}
+
+impl StyleSpan {
+    ///Returns the name of the style, which may carry attributes such as
+    ///`font;color=#ff0000`.
+    #[must_use]
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    ///Returns the index of the first character the style applies to, in
+    ///UTF-16 code units.
+    #[must_use]
+    pub fn get_first_char(&self) -> u32 {
+        self.first_char
+    }
+
+    ///Returns the index of the last character the style applies to,
+    ///inclusive, in UTF-16 code units.
+    #[must_use]
+    pub fn get_last_char(&self) -> u32 {
+        self.last_char
+    }
+}
+
+const STYLE_SPAN_END: u32 = 0xFFFF_FFFF;
+
+/// Size of the string pool header on disk: the 8 byte chunk header
+/// followed by five `u32` fields. This is not `size_of::<StringPoolHeader>()`,
+/// which depends on the in-memory layout of the struct.
+///
+/// Newer versions of the format may declare a larger header, whose extra
+/// fields are skipped.
+pub(crate) const STRINGPOOL_HEADER_SIZE: u16 = 8 + 20;
+
 impl StringPool {
-    fn read_strings<'a>(
-        header: &StringPoolHeader,
-        mut rest: &'a DekuRest,
-    ) -> Result<(&'a DekuRest, Vec<Rc<String>>), DekuError> {
-        const STRINGPOOL_HEADER_SIZE: usize = std::mem::size_of::<StringPoolHeader>();
+    pub(crate) fn read<'a>(
+        input: &'a [u8],
+        options: &ParseOptions,
+    ) -> Result<(&'a [u8], Self), ParseError> {
+        let endian = crate::binaryxml::detect_endian(input, ResourceType::StringPool);
+        Self::read_with_diagnostics(input, options, endian, 0, &mut Diagnostics::default())
+    }
 
-        assert_eq!(header.style_count, 0);
+    /// Reads a string pool stored in the given byte order, passing strings
+    /// and styles that cannot be decoded to `diagnostics`. `offset` is the
+    /// offset of the chunk in the document.
+    pub(crate) fn read_with_diagnostics<'a>(
+        input: &'a [u8],
+        options: &ParseOptions,
+        endian: Endian,
+        offset: usize,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<(&'a [u8], Self), ParseError> {
+        expect_chunk_type(input, endian, ResourceType::StringPool)?;
+        let (mut rest, header): (_, StringPoolHeader) = read_chunk(input, endian)?;
 
-        let flag_is_utf8 = (header.flags & (1 << 8)) != 0;
+        // Both offsets are from the start of the chunk, so neither can
+        // point past its end.
+        let size = header.chunk_header.size;
+        if header.string_start > size || header.style_start > size {
+            return Err(ParseError::InconsistentHeader);
+        }
 
-        let s = usize::try_from(header.chunk_header.size).unwrap() - STRINGPOOL_HEADER_SIZE;
+        let encoding = options
+            .force_encoding
+            .unwrap_or_else(|| StringEncoding::from_flags(header.flags));
+        // Unknown flags have been left as 0 by every version of the build
+        // tools, but do not change how the pool is read.
+        if header.flags & !KNOWN_FLAGS != 0 {
+            diagnostics.warn(at(offset)(ParseError::UnknownStringPoolFlags(header.flags)));
+        }
 
-        let mut string_pool_data = vec![0; s];
-        rest.read_exact(&mut string_pool_data).unwrap();
+        // The offsets follow the header, which may be larger than the
+        // fields that are read.
+        let header_size = header.chunk_header.header_size;
+        let extra_header = usize::from(header_size)
+            .checked_sub(usize::from(STRINGPOOL_HEADER_SIZE))
+            .ok_or(ParseError::InvalidChunkSize(u32::from(header_size)))?;
+        take(&mut rest, extra_header)?;
 
-        // Parse string offsets
-        let num_offsets = usize::try_from(header.string_count).unwrap();
-        let offsets = parse_offsets(&string_pool_data, num_offsets);
+        let s = to_usize(header.chunk_header.size)?
+            .checked_sub(usize::from(header_size))
+            .ok_or(ParseError::Overflow)?;
+
+        let string_pool_data = take(&mut rest, s)?;
 
-        let string_data_start =
-            usize::try_from(header.string_start).unwrap() - STRINGPOOL_HEADER_SIZE;
-        let string_data = &string_pool_data[string_data_start..];
+        // Every string and style has a 4 byte offset, so the counts can
+        // never exceed what fits in the chunk. Check this before trusting
+        // them for any allocation.
+        let max_offsets = string_pool_data.len() / 4;
+        let num_offsets = to_usize(header.string_count)?;
+        if num_offsets > max_offsets {
+            return Err(ParseError::StringCountOutOfBounds(header.string_count));
+        }
+        let num_style_offsets = to_usize(header.style_count)?;
+        if num_style_offsets > max_offsets - num_offsets {
+            return Err(ParseError::StringCountOutOfBounds(header.style_count));
+        }
 
-        let mut strings = Vec::with_capacity(usize::try_from(header.string_count).unwrap());
+        // Parse string offsets
+        let offsets = parse_offsets(string_pool_data, num_offsets, endian)?;
+        #[cfg(feature = "std")]
+        let mut all_offsets = offsets.clone();
+        #[cfg(feature = "std")]
+        let mut raw_style_data = Vec::new();
 
-        let parse_fn = if flag_is_utf8 {
-            parse_utf8_string
+        // A pool without strings may leave `string_start` at 0, so it is
+        // only checked when there are strings to read.
+        let string_data = if num_offsets == 0 {
+            &[]
         } else {
-            parse_utf16_string
+            let string_data_start = to_usize(header.string_start)?
+                .checked_sub(usize::from(header_size))
+                .ok_or(ParseError::Overflow)?;
+            string_pool_data
+                .get(string_data_start..)
+                .ok_or(ParseError::Overflow)?
         };
 
-        for offset in offsets {
-            strings.push(Rc::new(
-                parse_fn(string_data, usize::try_from(offset).unwrap())
-                    .map_err(|e| DekuError::Parse(e.to_string()))?,
-            ));
+        // The strings end where the style data starts, if there is any.
+        let string_data_len = match header.style_start.checked_sub(header.string_start) {
+            Some(len) if num_style_offsets > 0 && num_offsets > 0 => {
+                to_usize(len)?.min(string_data.len())
+            }
+            _ => string_data.len(),
+        };
+
+        let mut strings = Vec::with_capacity(num_offsets);
+
+        let parse_fn = |offset| {
+            let s = match encoding {
+                StringEncoding::Utf8 => parse_utf8_string(string_data, offset, options.lossy),
+                StringEncoding::Utf16 => {
+                    parse_utf16_string(string_data, offset, endian, options.lossy)
+                }
+            }?;
+            options.interior_nul.apply(s)
+        };
+
+        // Offsets may alias, and separate entries may hold the same text.
+        // Share a single `Arc` between all entries with identical content.
+        let mut unique: BTreeSet<Arc<String>> = BTreeSet::new();
+        let strings_position = offset as u64 + u64::from(header.string_start);
+        for string_offset in offsets {
+            let string = match check_offset(string_data, strings_position, string_offset)
+                .and_then(parse_fn)
+            {
+                Ok(string) => string,
+                Err(e) => {
+                    diagnostics.recover(at(offset)(e))?;
+                    String::new()
+                }
+            };
+            let string = match unique.get(&string) {
+                Some(existing) => existing.clone(),
+                None => {
+                    let string = Arc::new(string);
+                    unique.insert(string.clone());
+                    string
+                }
+            };
+            strings.push(string);
+        }
+
+        // Parse style offsets, which directly follow the string offsets
+        let mut styles = Vec::with_capacity(num_style_offsets);
+        // Some tools write a style count without any style data. The
+        // strings are still usable, so the styles are left out.
+        if num_style_offsets > 0 && header.style_start == 0 {
+            diagnostics.warn(at(offset)(ParseError::MissingStyleData(header.style_count)));
+        } else if num_style_offsets > 0 {
+            let style_offsets = string_pool_data
+                .get(num_offsets * 4..)
+                .ok_or(ParseError::Overflow)?;
+            let style_offsets = parse_offsets(style_offsets, num_style_offsets, endian)?;
+
+            let style_data_start = to_usize(header.style_start)?
+                .checked_sub(usize::from(header_size))
+                .ok_or(ParseError::Overflow)?;
+            let style_data = string_pool_data
+                .get(style_data_start..)
+                .ok_or(ParseError::Overflow)?;
+            #[cfg(feature = "std")]
+            {
+                all_offsets.extend_from_slice(&style_offsets);
+                raw_style_data = style_data.to_vec();
+            }
+
+            let styles_position = offset as u64 + u64::from(header.style_start);
+            for style_offset in style_offsets {
+                let spans = match check_offset(style_data, styles_position, style_offset)
+                    .and_then(|offset| parse_style_spans(style_data, offset, endian, &strings))
+                {
+                    Ok(spans) => spans,
+                    Err(e) => {
+                        diagnostics.recover(at(offset)(e))?;
+                        Vec::new()
+                    }
+                };
+                styles.push(spans);
+            }
         }
 
-        Ok((rest, strings))
+        Ok((
+            rest,
+            Self {
+                header,
+                encoding,
+                strings,
+                styles,
+                string_data: string_data[..string_data_len].to_vec(),
+                #[cfg(feature = "std")]
+                offsets: all_offsets,
+                #[cfg(feature = "std")]
+                style_data: raw_style_data,
+                #[cfg(feature = "std")]
+                endian,
+            },
+        ))
     }
 
-    pub(crate) fn get(&self, i: usize) -> Option<Rc<String>> {
-        if u32::try_from(i).unwrap() == u32::MAX {
+    ///Returns the string at index `i`.
+    #[must_use]
+    pub fn get(&self, i: usize) -> Option<Arc<String>> {
+        // `u32::MAX` marks a missing string, e.g. an attribute without a
+        // raw value.
+        if u32::try_from(i) == Ok(u32::MAX) {
             return None;
         }
 
         Some(self.strings.get(i)?.clone())
     }
+
+    ///Returns the string at index `i` as a `&str`, without adding a
+    ///reference to the shared string like [`StringPool::get`].
+    #[must_use]
+    pub fn get_str(&self, i: usize) -> Option<&str> {
+        if u32::try_from(i) == Ok(u32::MAX) {
+            return None;
+        }
+
+        self.strings.get(i).map(|s| s.as_str())
+    }
+
+    ///Returns the index of the first string equal to `s`.
+    ///
+    ///Sorted pools are binary searched, comparing strings by their UTF-16
+    ///code units like the Android framework does.
+    #[must_use]
+    pub fn index_of(&self, s: &str) -> Option<usize> {
+        if !self.is_sorted() {
+            return self.strings.iter().position(|string| string.as_str() == s);
+        }
+
+        let mut i = self
+            .strings
+            .binary_search_by(|string| string.encode_utf16().cmp(s.encode_utf16()))
+            .ok()?;
+        // The search may land on any of several equal strings.
+        while i > 0 && self.strings[i - 1].as_str() == s {
+            i -= 1;
+        }
+        Some(i)
+    }
+
+    ///Returns the number of strings in the pool.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    ///Returns `true` if the pool has no strings.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    ///Returns an iterator over every string in the pool, in pool order.
+    ///
+    ///The strings are borrowed from the pool, so this does not allocate.
+    pub fn get_strings(&self) -> impl Iterator<Item = &str> {
+        self.strings.iter().map(|s| s.as_str())
+    }
+
+    ///Returns the string data of the pool exactly as it was read, including
+    ///the length prefixes, terminators and padding around the strings.
+    ///Style data is not included.
+    #[must_use]
+    pub fn get_string_data(&self) -> &[u8] {
+        &self.string_data
+    }
+
+    ///Returns the raw flags of the pool header.
+    #[must_use]
+    pub fn get_flags(&self) -> u32 {
+        self.header.flags
+    }
+
+    ///Returns `true` if the pool is marked as sorted.
+    #[must_use]
+    pub fn is_sorted(&self) -> bool {
+        self.header.flags & SORTED_FLAG != 0
+    }
+
+    ///Returns the encoding of the strings in the pool.
+    #[must_use]
+    pub fn get_encoding(&self) -> StringEncoding {
+        self.encoding
+    }
+
+    ///Compares the strings of two pools by content, ignoring their order
+    ///and how many times each string appears.
+    ///
+    ///Strings only in `other` are added, and strings only in this pool are
+    ///removed.
+    #[must_use]
+    pub fn diff(&self, other: &StringPool) -> PoolDiff {
+        let ours: BTreeSet<&str> = self.get_strings().collect();
+        let theirs: BTreeSet<&str> = other.get_strings().collect();
+        let only_in = |pool: &StringPool, exclude: &BTreeSet<&str>| {
+            let mut seen = BTreeSet::new();
+            pool.strings
+                .iter()
+                .filter(|s| !exclude.contains(s.as_str()) && seen.insert(s.as_str()))
+                .cloned()
+                .collect()
+        };
+
+        PoolDiff {
+            added: only_in(other, &ours),
+            removed: only_in(self, &theirs),
+        }
+    }
+
+    ///Returns the style spans applied to the string at index `i`.
+    ///
+    ///Only the strings at the start of the pool can have styles. `None`
+    ///is returned for the strings after them.
+    #[must_use]
+    pub fn get_styles(&self, i: usize) -> Option<&[StyleSpan]> {
+        self.styles.get(i).map(Vec::as_slice)
+    }
+}
+
+///The differences between two string pools, as returned by
+///[`StringPool::diff`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolDiff {
+    added: Vec<Arc<String>>,
+    removed: Vec<Arc<String>>,
+}
+
+impl PoolDiff {
+    ///Returns an iterator over the added strings, in the order of the
+    ///other pool.
+    pub fn get_added(&self) -> impl Iterator<Item = &str> {
+        self.added.iter().map(|s| s.as_str())
+    }
+
+    ///Returns an iterator over the removed strings, in the order of this
+    ///pool.
+    pub fn get_removed(&self) -> impl Iterator<Item = &str> {
+        self.removed.iter().map(|s| s.as_str())
+    }
+
+    ///Returns `true` if both pools hold the same strings.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
 }
 
-fn parse_offsets(string_data: &[u8], count: usize) -> Vec<u32> {
+/// Converts a count or offset read from the input, failing with an
+/// `Overflow` error where it does not fit in a `usize`.
+pub(crate) fn to_usize(input: u32) -> Result<usize, ParseError> {
+    usize::try_from(input).map_err(|_| ParseError::Overflow)
+}
+
+/// Adds two offsets, failing with an `Overflow` error instead of wrapping
+/// around, which offsets from the file can do on 32-bit targets.
+fn add(a: usize, b: usize) -> Result<usize, ParseError> {
+    a.checked_add(b).ok_or(ParseError::Overflow)
+}
+
+/// Checks that `offset`, read from an offset table, points inside `data`,
+/// which starts at `position` in the document.
+fn check_offset(data: &[u8], position: u64, offset: u32) -> Result<usize, ParseError> {
+    match to_usize(offset) {
+        Ok(i) if i < data.len() => Ok(i),
+        _ => Err(ParseError::OffsetOutOfBounds {
+            position: position + u64::from(offset),
+            bound: position + data.len() as u64,
+        }),
+    }
+}
+
+fn parse_offsets(string_data: &[u8], count: usize, endian: Endian) -> Result<Vec<u32>, ParseError> {
     let mut offsets = Vec::with_capacity(count);
 
     for i in 0..count {
         let index = i * 4;
-        let offset = LittleEndian::read_u32(&string_data[index..index + 4]);
-        offsets.push(offset);
+        let offset = string_data
+            .get(index..index + 4)
+            .ok_or(ParseError::UnexpectedEof)?;
+        offsets.push(read_u32(endian, offset));
     }
 
-    offsets
+    Ok(offsets)
 }
 
-fn parse_utf16_string(string_data: &[u8], offset: usize) -> Result<String, ParseError> {
-    let len = LittleEndian::read_u16(&string_data[offset..offset + 2]);
+/// Parses the style spans at `offset`, looking their names up in
+/// `strings`.
+fn parse_style_spans(
+    style_data: &[u8],
+    offset: usize,
+    endian: Endian,
+    strings: &[Arc<String>],
+) -> Result<Vec<StyleSpan>, ParseError> {
+    let mut spans = Vec::new();
+    let mut rest = style_data.get(offset..).ok_or(ParseError::UnexpectedEof)?;
 
-    // Handles the case where the string is > 32767 characters
+    loop {
+        let name = rest.get(..4).ok_or(ParseError::UnexpectedEof)?;
+        if read_u32(endian, name) == STYLE_SPAN_END {
+            break;
+        }
+
+        let (remaining, span): (_, ResStringPoolSpan) = read_chunk(rest, endian)?;
+        rest = remaining;
+        let name = strings
+            .get(to_usize(span.name)?)
+            .ok_or(ParseError::StringNotFound(span.name))?;
+        spans.push(StyleSpan {
+            name: name.clone(),
+            first_char: span.first_char,
+            last_char: span.last_char,
+        });
+    }
+
+    Ok(spans)
+}
+
+fn parse_utf16_string(
+    string_data: &[u8],
+    offset: usize,
+    endian: Endian,
+    lossy: bool,
+) -> Result<String, ParseError> {
+    let mut len = usize::from(read_u16_at(string_data, offset, endian)?);
+    let mut string_start = add(offset, 2)?;
+
+    // The length counts UTF-16 code units, so a surrogate pair counts as
+    // two. Handles the case where the string is > 32767 code units. The
+    // high bit signals that the length continues into a second u16.
     if is_high_bit_set_16(len) {
-        unimplemented!()
+        let low = read_u16_at(string_data, string_start, endian)?;
+        len = ((len & 0x7FFF) << 16) | usize::from(low);
+        string_start = add(string_start, 2)?;
     }
 
-    // This needs to change if we ever implement support for long strings
-    let string_start = offset + 2;
+    let byte_len = len.checked_mul(2).ok_or(ParseError::Overflow)?;
+    let s: Vec<u16> = slice_at(string_data, string_start, byte_len)?
+        .chunks_exact(2)
+        .map(|c| read_u16(endian, c))
+        .collect();
 
-    let mut s = Vec::with_capacity(len.into());
-    for i in 0..len {
-        let index = string_start + usize::try_from(i * 2).unwrap();
-        let char = LittleEndian::read_u16(&string_data[index..index + 2]);
-        s.push(char);
+    if lossy {
+        return Ok(String::from_utf16_lossy(&s));
     }
 
     let s = String::from_utf16(&s).map_err(ParseError::Utf16StringParseError)?;
     Ok(s)
 }
 
-fn is_high_bit_set_16(input: u16) -> bool {
+fn is_high_bit_set_16(input: usize) -> bool {
     input & (1 << 15) != 0
 }
 
-fn parse_utf8_string(string_data: &[u8], offset: usize) -> Result<String, ParseError> {
-    let len = string_data[offset + 1];
+fn parse_utf8_string(string_data: &[u8], offset: usize, lossy: bool) -> Result<String, ParseError> {
+    // The character count comes first but is not needed to decode the
+    // string, only the byte count that follows it.
+    let (_, char_count_size) = parse_utf8_length(string_data, offset)?;
+    let byte_count_start = add(offset, char_count_size)?;
+    let (len, byte_count_size) = parse_utf8_length(string_data, byte_count_start)?;
 
-    // Handles the case where the length value has high bit set
-    // Not quite clear if the UTF-8 encoding actually has this but
-    // perform the check anyway...
-    if is_high_bit_set_8(len) {
-        unimplemented!()
-    }
+    let string_start = add(byte_count_start, byte_count_size)?;
 
-    // This needs to change if we ever implement support for long strings
-    let string_start = offset + 2;
+    let s = slice_at(string_data, string_start, len)?.to_vec();
 
-    let mut s = Vec::with_capacity(len.into());
-    for i in 0..len {
-        let index = string_start + usize::try_from(i).unwrap();
-        let char = string_data[index];
-        s.push(char);
+    if lossy {
+        return Ok(String::from_utf8_lossy(&s).into_owned());
     }
 
     let s = String::from_utf8(s).map_err(ParseError::Utf8StringParseError)?;
     Ok(s)
 }
 
-fn is_high_bit_set_8(input: u8) -> bool {
+/// Reads a UTF-8 string pool length field, returning the length and the
+/// number of bytes used to encode it.
+fn parse_utf8_length(string_data: &[u8], offset: usize) -> Result<(usize, usize), ParseError> {
+    let byte_at = |i: usize| {
+        string_data
+            .get(i)
+            .map(|&b| usize::from(b))
+            .ok_or(ParseError::UnexpectedEof)
+    };
+    let len = byte_at(offset)?;
+
+    // Handles the case where the length is > 127. The high bit signals
+    // that the length continues into a second byte.
+    if is_high_bit_set_8(len) {
+        let low = byte_at(add(offset, 1)?)?;
+        Ok((((len & 0x7F) << 8) | low, 2))
+    } else {
+        Ok((len, 1))
+    }
+}
+
+/// Returns the `len` bytes of `data` starting at `start`, failing with an
+/// `UnexpectedEof` error if they run past its end.
+fn slice_at(data: &[u8], start: usize, len: usize) -> Result<&[u8], ParseError> {
+    data.get(start..add(start, len)?)
+        .ok_or(ParseError::UnexpectedEof)
+}
+
+fn read_u16_at(data: &[u8], offset: usize, endian: Endian) -> Result<u16, ParseError> {
+    slice_at(data, offset, 2).map(|b| read_u16(endian, b))
+}
+
+fn is_high_bit_set_8(input: usize) -> bool {
     input & (1 << 7) != 0
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_type::RES_STRING_POOL_TYPE;
+
+    /// Builds a string pool chunk from already encoded string entries.
+    fn build_string_pool(
+        flags: u32,
+        entries: &[Vec<u8>],
+        styles: &[Vec<ResStringPoolSpan>],
+    ) -> Vec<u8> {
+        let string_start = 28 + 4 * (entries.len() + styles.len());
+
+        let mut offsets = Vec::new();
+        let mut string_data = Vec::new();
+        for entry in entries {
+            offsets.extend_from_slice(&u32::try_from(string_data.len()).unwrap().to_le_bytes());
+            string_data.extend_from_slice(entry);
+        }
+        while string_data.len() % 4 != 0 {
+            string_data.push(0);
+        }
+
+        let mut style_data = Vec::new();
+        for style in styles {
+            offsets.extend_from_slice(&u32::try_from(style_data.len()).unwrap().to_le_bytes());
+            for span in style {
+                style_data.extend_from_slice(&span.name.to_le_bytes());
+                style_data.extend_from_slice(&span.first_char.to_le_bytes());
+                style_data.extend_from_slice(&span.last_char.to_le_bytes());
+            }
+            style_data.extend_from_slice(&STYLE_SPAN_END.to_le_bytes());
+        }
+        let style_start = if styles.is_empty() {
+            0
+        } else {
+            string_start + string_data.len()
+        };
+
+        let size = string_start + string_data.len() + style_data.len();
+        let mut data = Vec::new();
+        data.extend_from_slice(&RES_STRING_POOL_TYPE.to_le_bytes());
+        data.extend_from_slice(&28_u16.to_le_bytes());
+        data.extend_from_slice(&u32::try_from(size).unwrap().to_le_bytes());
+        data.extend_from_slice(&u32::try_from(entries.len()).unwrap().to_le_bytes());
+        data.extend_from_slice(&u32::try_from(styles.len()).unwrap().to_le_bytes());
+        data.extend_from_slice(&flags.to_le_bytes());
+        data.extend_from_slice(&u32::try_from(string_start).unwrap().to_le_bytes());
+        data.extend_from_slice(&u32::try_from(style_start).unwrap().to_le_bytes());
+        data.extend_from_slice(&offsets);
+        data.extend_from_slice(&string_data);
+        data.extend_from_slice(&style_data);
+        data
+    }
+
+    fn encode_utf16(s: &str) -> Vec<u8> {
+        let units: Vec<u16> = s.encode_utf16().collect();
+        let len = units.len();
+
+        let mut data = Vec::new();
+        if len > 0x7FFF {
+            let high = u16::try_from(len >> 16).unwrap() | 0x8000;
+            data.extend_from_slice(&high.to_le_bytes());
+            data.extend_from_slice(&u16::try_from(len & 0xFFFF).unwrap().to_le_bytes());
+        } else {
+            data.extend_from_slice(&u16::try_from(len).unwrap().to_le_bytes());
+        }
+        for unit in units {
+            data.extend_from_slice(&unit.to_le_bytes());
+        }
+        data.extend_from_slice(&[0, 0]);
+        data
+    }
+
+    fn encode_utf8(s: &str) -> Vec<u8> {
+        let mut data = Vec::new();
+        for len in [s.chars().count(), s.len()] {
+            if len > 0x7F {
+                data.push(u8::try_from(len >> 8).unwrap() | 0x80);
+                data.push(u8::try_from(len & 0xFF).unwrap());
+            } else {
+                data.push(u8::try_from(len).unwrap());
+            }
+        }
+        data.extend_from_slice(s.as_bytes());
+        data.push(0);
+        data
+    }
+
+    #[test]
+    fn test_first_string() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+
+        let (_, string_pool) = StringPool::read(&data[8..], &ParseOptions::default()).unwrap();
+        assert_eq!(
+            string_pool.header.chunk_header.header_size,
+            STRINGPOOL_HEADER_SIZE
+        );
+        assert_eq!(*string_pool.get(0).unwrap(), "versionCode");
+    }
+
+    #[test]
+    fn test_header_size_independent_of_layout() {
+        // The header is read field by field, so the bytes consumed match the
+        // on-disk size whatever the size of the struct is on this target.
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let input = &data[8..];
+
+        let (rest, _): (_, StringPoolHeader) = read_chunk(input, Endian::Little).unwrap();
+        assert_eq!(
+            input.len() - rest.len(),
+            usize::from(STRINGPOOL_HEADER_SIZE)
+        );
+    }
+
+    #[test]
+    fn test_larger_header() {
+        let span = ResStringPoolSpan {
+            name: 1,
+            first_char: 0,
+            last_char: 1,
+        };
+        let entries = [encode_utf8("ab"), encode_utf8("c")];
+        let mut data = build_string_pool(UTF8_FLAG, &entries, &[vec![span]]);
+
+        // Grow the header by a field the parser does not know about.
+        data.splice(28..28, [0xAA; 4]);
+        let grow = |data: &mut [u8], at: usize| {
+            let value = u32::from_le_bytes(data[at..at + 4].try_into().unwrap());
+            data[at..at + 4].copy_from_slice(&(value + 4).to_le_bytes());
+        };
+        data[2..4].copy_from_slice(&32_u16.to_le_bytes());
+        grow(&mut data, 4);
+        grow(&mut data, 20);
+        grow(&mut data, 24);
+
+        let (rest, string_pool) = StringPool::read(&data, &ParseOptions::default()).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(*string_pool.get(0).unwrap(), "ab");
+        assert_eq!(*string_pool.get(1).unwrap(), "c");
+        assert_eq!(string_pool.get_styles(0).unwrap()[0].get_name(), "c");
+
+        // A header smaller than its fields is rejected.
+        data[2..4].copy_from_slice(&20_u16.to_le_bytes());
+        assert!(matches!(
+            StringPool::read(&data, &ParseOptions::default()),
+            Err(ParseError::InvalidChunkSize(20))
+        ));
+    }
+
+    #[test]
+    fn test_get_out_of_range() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+
+        let (_, string_pool) = StringPool::read(&data[8..], &ParseOptions::default()).unwrap();
+        assert!(string_pool.get(string_pool.len() + 5).is_none());
+        assert!(string_pool.get(usize::MAX).is_none());
+        assert!(string_pool
+            .get(usize::try_from(u32::MAX).unwrap())
+            .is_none());
+    }
+
+    #[test]
+    fn test_get_str() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+
+        let (_, string_pool) = StringPool::read(&data[8..], &ParseOptions::default()).unwrap();
+        for i in 0..string_pool.len() {
+            assert_eq!(
+                string_pool.get_str(i),
+                string_pool.get(i).as_deref().map(String::as_str)
+            );
+        }
+        assert!(string_pool.get_str(string_pool.len()).is_none());
+        assert!(string_pool
+            .get_str(usize::try_from(u32::MAX).unwrap())
+            .is_none());
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = ["name", "label", "icon", "label"].map(encode_utf8);
+        let new = ["icon", "name", "theme", "theme"].map(encode_utf8);
+        let (_, old) = StringPool::read(
+            &build_string_pool(UTF8_FLAG, &old, &[]),
+            &ParseOptions::default(),
+        )
+        .unwrap();
+        let (_, new) = StringPool::read(
+            &build_string_pool(UTF8_FLAG, &new, &[]),
+            &ParseOptions::default(),
+        )
+        .unwrap();
+
+        let diff = old.diff(&new);
+        assert!(diff.get_added().eq(["theme"]));
+        assert!(diff.get_removed().eq(["label"]));
+        assert!(!diff.is_empty());
+        assert!(old.diff(&old).is_empty());
+    }
+
+    #[test]
+    fn test_encoding_from_flags() {
+        assert_eq!(StringEncoding::from_flags(0), StringEncoding::Utf16);
+        assert_eq!(StringEncoding::from_flags(0x100), StringEncoding::Utf8);
+        assert_eq!(
+            StringEncoding::from_flags(SORTED_FLAG),
+            StringEncoding::Utf16
+        );
+        assert_eq!(
+            StringEncoding::from_flags(0x100 | SORTED_FLAG),
+            StringEncoding::Utf8
+        );
+        // Neighbouring bits do not select UTF-8.
+        assert_eq!(
+            StringEncoding::from_flags(0x80 | 0x200),
+            StringEncoding::Utf16
+        );
+    }
+
+    #[test]
+    fn test_unknown_flags() {
+        let data = build_string_pool(UTF8_FLAG | 0x0001_0000, &[encode_utf8("a")], &[]);
+        let (_, string_pool) = StringPool::read(&data, &ParseOptions::default()).unwrap();
+        assert_eq!(string_pool.get_encoding(), StringEncoding::Utf8);
+
+        let mut diagnostics = Diagnostics::collecting();
+        StringPool::read_with_diagnostics(
+            &data,
+            &ParseOptions::default(),
+            Endian::Little,
+            8,
+            &mut diagnostics,
+        )
+        .unwrap();
+        let diagnostics = diagnostics.into_vec();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].get_offset(), 8);
+        assert_eq!(
+            diagnostics[0].get_message(),
+            "string pool has unknown flags set: 0x00010100"
+        );
+    }
+
+    #[test]
+    fn test_force_encoding() {
+        // UTF-16 strings in a pool flagged as UTF-8.
+        let entries = [encode_utf16("first"), encode_utf16("second")];
+        let data = build_string_pool(UTF8_FLAG, &entries, &[]);
+
+        let (_, string_pool) = StringPool::read(&data, &ParseOptions::default()).unwrap();
+        assert_ne!(*string_pool.get(0).unwrap(), "first");
+
+        let options = ParseOptions {
+            force_encoding: Some(StringEncoding::Utf16),
+            ..ParseOptions::default()
+        };
+        let (_, string_pool) = StringPool::read(&data, &options).unwrap();
+        assert_eq!(string_pool.get_encoding(), StringEncoding::Utf16);
+        assert_eq!(*string_pool.get(0).unwrap(), "first");
+        assert_eq!(*string_pool.get(1).unwrap(), "second");
+    }
+
+    #[test]
+    fn test_interior_nul() {
+        let entries = [encode_utf16("a\0b"), encode_utf16("c")];
+        let data = build_string_pool(0, &entries, &[]);
+        let read = |interior_nul| {
+            let options = ParseOptions {
+                interior_nul,
+                ..ParseOptions::default()
+            };
+            StringPool::read(&data, &options).map(|(_, string_pool)| string_pool)
+        };
+
+        let string_pool = read(NulHandling::Preserve).unwrap();
+        assert_eq!(*string_pool.get(0).unwrap(), "a\0b");
+        let string_pool = read(NulHandling::Strip).unwrap();
+        assert_eq!(*string_pool.get(0).unwrap(), "ab");
+        assert_eq!(*string_pool.get(1).unwrap(), "c");
+        let Err(ParseError::At { source, .. }) = read(NulHandling::Reject) else {
+            panic!("interior NUL was accepted");
+        };
+        assert!(matches!(*source, ParseError::InteriorNul));
+    }
+
+    #[test]
+    fn test_index_of() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+
+        let (_, string_pool) = StringPool::read(&data[8..], &ParseOptions::default()).unwrap();
+        let index = string_pool.index_of("manifest").unwrap();
+        assert_eq!(*string_pool.get(index).unwrap(), "manifest");
+        assert_eq!(string_pool.index_of("versionCode"), Some(0));
+        assert_eq!(string_pool.index_of("does-not-exist"), None);
+    }
+
+    #[test]
+    fn test_sorted_pool() {
+        let data = build_string_pool(SORTED_FLAG | UTF8_FLAG, &[], &[]);
+        let (_, string_pool) = StringPool::read(&data, &ParseOptions::default()).unwrap();
+        assert_eq!(string_pool.get_flags(), SORTED_FLAG | UTF8_FLAG);
+        assert!(string_pool.is_sorted());
+
+        // U+1F600 is stored as a surrogate pair, which sorts before U+FF61.
+        let strings = ["Z", "a", "b", "b", "\u{e9}", "\u{1f600}", "\u{ff61}"];
+        let entries: Vec<_> = strings.iter().map(|s| encode_utf16(s)).collect();
+        let data = build_string_pool(SORTED_FLAG, &entries, &[]);
+        let (_, string_pool) = StringPool::read(&data, &ParseOptions::default()).unwrap();
+        assert!(string_pool.is_sorted());
+        for (i, s) in strings.iter().enumerate() {
+            let expected = if *s == "b" { 2 } else { i };
+            assert_eq!(string_pool.index_of(s), Some(expected));
+        }
+        assert_eq!(string_pool.index_of("c"), None);
+
+        let data = build_string_pool(0, &entries, &[]);
+        let (_, string_pool) = StringPool::read(&data, &ParseOptions::default()).unwrap();
+        assert!(!string_pool.is_sorted());
+        assert_eq!(string_pool.index_of("b"), Some(2));
+    }
+
+    #[test]
+    fn test_string_data() {
+        let span = ResStringPoolSpan {
+            name: 1,
+            first_char: 0,
+            last_char: 1,
+        };
+        let entries = [encode_utf8("ab"), encode_utf8("c")];
+        let data = build_string_pool(UTF8_FLAG, &entries, &[vec![span]]);
+
+        let (_, string_pool) = StringPool::read(&data, &ParseOptions::default()).unwrap();
+        assert_eq!(
+            string_pool.get_string_data(),
+            [2, 2, b'a', b'b', 0, 1, 1, b'c', 0, 0, 0, 0]
+        );
+
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+        let (_, string_pool) = StringPool::read(&data[8..], &ParseOptions::default()).unwrap();
+        let string_start = 8 + usize::try_from(string_pool.header.string_start).unwrap();
+        let string_end = 8 + usize::try_from(string_pool.header.chunk_header.size).unwrap();
+        assert_eq!(
+            string_pool.get_string_data(),
+            &data[string_start..string_end]
+        );
+    }
+
+    #[test]
+    fn test_len() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+
+        let (_, string_pool) = StringPool::read(&data[8..], &ParseOptions::default()).unwrap();
+        assert_eq!(
+            string_pool.len(),
+            usize::try_from(string_pool.header.string_count).unwrap()
+        );
+        assert!(!string_pool.is_empty());
+    }
+
+    #[test]
+    fn test_empty_pool() {
+        for flags in [0, UTF8_FLAG] {
+            let mut data = build_string_pool(flags, &[], &[]);
+            let (rest, string_pool) = StringPool::read(&data, &ParseOptions::default()).unwrap();
+            assert!(rest.is_empty());
+            assert!(string_pool.is_empty());
+            assert_eq!(string_pool.get(0), None);
+
+            // Some tools leave the string start at 0 when there are no
+            // strings.
+            data[20..24].copy_from_slice(&0_u32.to_le_bytes());
+            let (_, string_pool) = StringPool::read(&data, &ParseOptions::default()).unwrap();
+            assert!(string_pool.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_missing_terminator() {
+        // Each final entry ends exactly at the chunk boundary, with no NUL
+        // and no padding after it.
+        let mut utf8 = encode_utf8("ab");
+        utf8.pop();
+        let data = build_string_pool(UTF8_FLAG, &[encode_utf8("first"), utf8], &[]);
+        assert_eq!(data.last(), Some(&b'b'));
+        let (_, string_pool) = StringPool::read(&data, &ParseOptions::default()).unwrap();
+        assert_eq!(string_pool.get(1).unwrap().as_str(), "ab");
+
+        let mut utf16 = encode_utf16("a");
+        utf16.truncate(utf16.len() - 2);
+        let data = build_string_pool(0, &[encode_utf16("name"), utf16], &[]);
+        assert_eq!(data[data.len() - 2..], [b'a', 0]);
+        let (_, string_pool) = StringPool::read(&data, &ParseOptions::default()).unwrap();
+        assert_eq!(string_pool.get(1).unwrap().as_str(), "a");
+    }
+
+    #[test]
+    fn test_long_utf8_string() {
+        let long = "b".repeat(200);
+        let data = build_string_pool(UTF8_FLAG, &[encode_utf8(&long), encode_utf8("short")], &[]);
+
+        let (_, string_pool) = StringPool::read(&data, &ParseOptions::default()).unwrap();
+        assert_eq!(*string_pool.get(0).unwrap(), long);
+        assert_eq!(*string_pool.get(1).unwrap(), "short");
+    }
+
+    #[test]
+    fn test_long_utf16_string() {
+        let long = "a".repeat(40000);
+        let data = build_string_pool(0, &[encode_utf16(&long), encode_utf16("short")], &[]);
+
+        let (_, string_pool) = StringPool::read(&data, &ParseOptions::default()).unwrap();
+        assert_eq!(*string_pool.get(0).unwrap(), long);
+        assert_eq!(*string_pool.get(1).unwrap(), "short");
+    }
+
+    #[test]
+    fn test_large_pool() {
+        let strings: Vec<String> = (0..20000).map(|i| format!("string{i}")).collect();
+
+        let utf8: Vec<_> = strings.iter().map(|s| encode_utf8(s)).collect();
+        let (_, string_pool) = StringPool::read(
+            &build_string_pool(UTF8_FLAG, &utf8, &[]),
+            &ParseOptions::default(),
+        )
+        .unwrap();
+        assert!(string_pool.strings.iter().map(|s| s.as_str()).eq(&strings));
+
+        let utf16: Vec<_> = strings.iter().map(|s| encode_utf16(s)).collect();
+        let (_, string_pool) =
+            StringPool::read(&build_string_pool(0, &utf16, &[]), &ParseOptions::default()).unwrap();
+        assert!(string_pool.strings.iter().map(|s| s.as_str()).eq(&strings));
+    }
+
+    #[test]
+    fn test_truncated_offset_table() {
+        let data = include_bytes!("../examples/AndroidManifest.xml");
+
+        // Skip the document chunk header and cut the string pool off
+        // halfway through its offsets.
+        let truncated = &data[8..8 + 28 + 40];
+        assert!(StringPool::read(truncated, &ParseOptions::default()).is_err());
+        assert!(crate::parse(&data[..8 + 28 + 40]).is_err());
+    }
+
+    #[test]
+    fn test_inconsistent_header() {
+        let data = build_string_pool(UTF8_FLAG, &[encode_utf8("first")], &[]);
+        let size = u32::from_le_bytes(data[4..8].try_into().unwrap());
+
+        for field in [20, 24] {
+            let mut inconsistent = data.clone();
+            inconsistent[field..field + 4].copy_from_slice(&(size + 1).to_le_bytes());
+            assert!(matches!(
+                StringPool::read(&inconsistent, &ParseOptions::default()),
+                Err(ParseError::InconsistentHeader)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_offset_overflow() {
+        let data = [0x81, 0x00, 0xff, 0xff];
+        assert!(matches!(
+            slice_at(&data, usize::MAX - 1, 2),
+            Err(ParseError::Overflow)
+        ));
+        assert!(matches!(
+            parse_utf16_string(&data, usize::MAX - 1, Endian::Little, false),
+            Err(ParseError::Overflow)
+        ));
+
+        // A string offset at the very end of the range.
+        let mut data = build_string_pool(UTF8_FLAG, &[encode_utf8("first")], &[]);
+        data[28..32].copy_from_slice(&u32::MAX.to_le_bytes());
+        let (_, string_pool) = StringPool::read_with_diagnostics(
+            &data,
+            &ParseOptions::default(),
+            Endian::Little,
+            0,
+            &mut Diagnostics::collecting(),
+        )
+        .unwrap();
+        assert_eq!(string_pool.get_str(0), Some(""));
+    }
+
+    #[test]
+    fn test_offset_out_of_bounds() {
+        let mut data = build_string_pool(
+            UTF8_FLAG,
+            &[encode_utf8("first"), encode_utf8("second")],
+            &[],
+        );
+        // Point the second string 4 bytes past the end of the chunk.
+        let size = data.len();
+        data[32..36].copy_from_slice(&u32::try_from(size - 36 + 4).unwrap().to_le_bytes());
+
+        let Err(ParseError::At { offset, source }) = StringPool::read_with_diagnostics(
+            &data,
+            &ParseOptions::default(),
+            Endian::Little,
+            100,
+            &mut Diagnostics::default(),
+        ) else {
+            panic!("offset past the chunk was accepted");
+        };
+        assert_eq!(offset, 100);
+        assert!(matches!(
+            *source,
+            ParseError::OffsetOutOfBounds { position, bound }
+                if position == 100 + size as u64 + 4 && bound == 100 + size as u64
+        ));
+    }
+
+    #[test]
+    fn test_style_spans() {
+        let spans = vec![
+            ResStringPoolSpan {
+                name: 1,
+                first_char: 0,
+                last_char: 3,
+            },
+            ResStringPoolSpan {
+                name: 2,
+                first_char: 5,
+                last_char: 8,
+            },
+        ];
+        let entries = [
+            encode_utf16("bold text"),
+            encode_utf16("b"),
+            encode_utf16("i"),
+        ];
+        let data = build_string_pool(0, &entries, &[spans]);
+
+        let (_, string_pool) = StringPool::read(&data, &ParseOptions::default()).unwrap();
+        assert_eq!(*string_pool.get(0).unwrap(), "bold text");
+        let styles = string_pool.get_styles(0).unwrap();
+        assert_eq!(styles.len(), 2);
+        assert_eq!(styles[0].get_name(), "b");
+        assert_eq!(styles[0].get_first_char(), 0);
+        assert_eq!(styles[0].get_last_char(), 3);
+        assert_eq!(styles[1].get_name(), "i");
+        assert_eq!(styles[1].get_first_char(), 5);
+        assert_eq!(styles[1].get_last_char(), 8);
+        assert!(string_pool.get_styles(1).is_none());
+
+        let spans = vec![ResStringPoolSpan {
+            name: 3,
+            first_char: 0,
+            last_char: 3,
+        }];
+        let data = build_string_pool(0, &entries, &[spans]);
+        assert!(matches!(
+            StringPool::read(&data, &ParseOptions::default()),
+            Err(ParseError::At { source, .. }) if matches!(*source, ParseError::StringNotFound(3))
+        ));
+    }
+
+    #[test]
+    fn test_style_count_without_style_data() {
+        let spans = vec![ResStringPoolSpan {
+            name: 1,
+            first_char: 0,
+            last_char: 3,
+        }];
+        let entries = [encode_utf8("bold text"), encode_utf8("b")];
+        let mut data = build_string_pool(UTF8_FLAG, &entries, &[spans]);
+        data[24..28].copy_from_slice(&0_u32.to_le_bytes());
+
+        let (_, string_pool) = StringPool::read(&data, &ParseOptions::default()).unwrap();
+        assert_eq!(string_pool.get_str(0), Some("bold text"));
+        assert_eq!(string_pool.get_str(1), Some("b"));
+        assert!(string_pool.get_styles(0).is_none());
+
+        let mut diagnostics = Diagnostics::collecting();
+        StringPool::read_with_diagnostics(
+            &data,
+            &ParseOptions::default(),
+            Endian::Little,
+            8,
+            &mut diagnostics,
+        )
+        .unwrap();
+        let diagnostics = diagnostics.into_vec();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "string pool has 1 styles but no style data at offset 8"
+        );
+    }
+
+    #[test]
+    fn test_chunk_after_styles() {
+        let spans = vec![ResStringPoolSpan {
+            name: 1,
+            first_char: 0,
+            last_char: 3,
+        }];
+        let entries = [encode_utf8("bold text"), encode_utf8("b")];
+        let mut data = build_string_pool(UTF8_FLAG, &entries, &[spans]);
+
+        // An empty resource map follows the pool.
+        let next = [0x80, 0x01, 0x08, 0x00, 0x08, 0x00, 0x00, 0x00];
+        data.extend_from_slice(&next);
+
+        let (rest, string_pool) = StringPool::read(&data, &ParseOptions::default()).unwrap();
+        assert_eq!(string_pool.get_styles(0).unwrap().len(), 1);
+        assert_eq!(rest, next);
+    }
+
+    #[test]
+    fn test_surrogate_pairs() {
+        // 20000 emoji take 40000 code units, which needs the long length.
+        let long = "\u{1F600}".repeat(20000);
+        let strings = ["\u{1F600}", "a\u{1F600}b", &long];
+        let entries: Vec<_> = strings.iter().map(|s| encode_utf16(s)).collect();
+        let data = build_string_pool(0, &entries, &[]);
+
+        let (_, string_pool) = StringPool::read(&data, &ParseOptions::default()).unwrap();
+        for (i, s) in strings.iter().enumerate() {
+            assert_eq!(*string_pool.get(i).unwrap(), *s);
+        }
+    }
+
+    #[test]
+    fn test_shared_strings() {
+        let entries: Vec<_> = ["a", "b", "a", "c"]
+            .iter()
+            .map(|s| encode_utf8(s))
+            .collect();
+        let mut data = build_string_pool(UTF8_FLAG, &entries, &[]);
+        // Point the last offset at the first entry.
+        data[28 + 12..28 + 16].copy_from_slice(&0_u32.to_le_bytes());
+
+        let (_, string_pool) = StringPool::read(&data, &ParseOptions::default()).unwrap();
+        let strings = &string_pool.strings;
+        assert_eq!(strings.len(), 4);
+        assert_eq!(*strings[3], "a");
+        assert!(Arc::ptr_eq(&strings[0], &strings[2]));
+        assert!(Arc::ptr_eq(&strings[0], &strings[3]));
+        assert!(!Arc::ptr_eq(&strings[0], &strings[1]));
+    }
+
+    #[test]
+    fn test_lossy_decoding() {
+        // A lone high surrogate is not valid UTF-16.
+        let entry = [1_u16.to_le_bytes(), 0xD800_u16.to_le_bytes(), [0, 0]].concat();
+        let data = build_string_pool(0, &[entry], &[]);
+
+        assert!(StringPool::read(&data, &ParseOptions::default()).is_err());
+
+        let options = ParseOptions {
+            lossy: true,
+            ..ParseOptions::default()
+        };
+        let (_, string_pool) = StringPool::read(&data, &options).unwrap();
+        assert_eq!(*string_pool.get(0).unwrap(), "\u{FFFD}");
+    }
+
+    #[test]
+    fn test_big_endian() {
+        let mut string_data = Vec::new();
+        let mut offsets = Vec::new();
+        for s in ["hello", "w\u{f6}rld"] {
+            offsets.extend_from_slice(&u32::try_from(string_data.len()).unwrap().to_be_bytes());
+            let units: Vec<u16> = s.encode_utf16().collect();
+            string_data.extend_from_slice(&u16::try_from(units.len()).unwrap().to_be_bytes());
+            for unit in units {
+                string_data.extend_from_slice(&unit.to_be_bytes());
+            }
+            string_data.extend_from_slice(&[0, 0]);
+        }
+
+        let mut style_data = Vec::new();
+        for value in [1, 0, 4, STYLE_SPAN_END] {
+            style_data.extend_from_slice(&u32::to_be_bytes(value));
+        }
+        offsets.extend_from_slice(&0_u32.to_be_bytes());
+
+        let string_start = 28 + offsets.len();
+        let style_start = string_start + string_data.len();
+        let size = style_start + style_data.len();
+        let mut data = Vec::new();
+        data.extend_from_slice(&RES_STRING_POOL_TYPE.to_be_bytes());
+        data.extend_from_slice(&28_u16.to_be_bytes());
+        for value in [size, 2, 1, 0, string_start, style_start] {
+            data.extend_from_slice(&u32::try_from(value).unwrap().to_be_bytes());
+        }
+        data.extend_from_slice(&offsets);
+        data.extend_from_slice(&string_data);
+        data.extend_from_slice(&style_data);
+
+        let (_, string_pool) = StringPool::read(&data, &ParseOptions::default()).unwrap();
+        assert_eq!(string_pool.encoding, StringEncoding::Utf16);
+        assert_eq!(string_pool.get(0).unwrap().as_str(), "hello");
+        assert_eq!(string_pool.get(1).unwrap().as_str(), "w\u{f6}rld");
+        let styles = string_pool.get_styles(0).unwrap();
+        assert_eq!(styles.len(), 1);
+        assert_eq!(styles[0].get_name(), "w\u{f6}rld");
+        assert_eq!(styles[0].get_first_char(), 0);
+        assert_eq!(styles[0].get_last_char(), 4);
+    }
+}
@@ -0,0 +1,278 @@
+use alloc::string::String;
+use alloc::sync::Arc;
+use core::fmt;
+
+use crate::binaryxml::{ResourceValue, ResourceValueType};
+use crate::stringpool::StringPool;
+use crate::ParseError;
+
+///Enum representing a decoded typed attribute value.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum AttributeValue {
+    ///A string from the document's string pool.
+    String(Arc<String>),
+    ///A reference to another resource by resource ID.
+    Reference(u32),
+    ///A boolean value.
+    Boolean(bool),
+    ///An integer stored in decimal form.
+    Integer(i32),
+    ///An integer stored in hexadecimal form.
+    Hex(u32),
+    ///A floating point value.
+    Float(f32),
+    ///A dimension such as `16dip`, kept as the packed data word.
+    Dimension(u32),
+    ///A fraction such as `50%`, kept as the packed data word.
+    Fraction(u32),
+    ///A color such as `#ff0000`.
+    Color(Color),
+    ///A value of a type that is not decoded, kept as the raw type byte
+    ///and data word.
+    Raw { data_type: u8, data: u32 },
+}
+
+impl AttributeValue {
+    pub(crate) fn new(value: &ResourceValue, string_pool: &StringPool) -> Result<Self, ParseError> {
+        let data = value.data;
+
+        Ok(match value.get_type() {
+            Some(ResourceValueType::String) => AttributeValue::String(
+                string_pool
+                    .get(usize::try_from(data).map_err(|_| ParseError::Overflow)?)
+                    .ok_or(ParseError::StringNotFound(data))?,
+            ),
+            Some(ResourceValueType::Reference) => AttributeValue::Reference(data),
+            Some(ResourceValueType::Boolean) => AttributeValue::Boolean(data != 0),
+            Some(ResourceValueType::Dec) => {
+                AttributeValue::Integer(i32::from_ne_bytes(data.to_ne_bytes()))
+            }
+            Some(ResourceValueType::Hex) => AttributeValue::Hex(data),
+            Some(ResourceValueType::Float) => AttributeValue::Float(f32::from_bits(data)),
+            Some(ResourceValueType::Dimension) => AttributeValue::Dimension(data),
+            Some(ResourceValueType::Fraction) => AttributeValue::Fraction(data),
+            Some(ResourceValueType::ColorArgb8) => {
+                AttributeValue::Color(Color::new(ColorFormat::Argb8, data))
+            }
+            Some(ResourceValueType::ColorRgb8) => {
+                AttributeValue::Color(Color::new(ColorFormat::Rgb8, data))
+            }
+            Some(ResourceValueType::ColorArgb4) => {
+                AttributeValue::Color(Color::new(ColorFormat::Argb4, data))
+            }
+            Some(ResourceValueType::ColorRgb4) => {
+                AttributeValue::Color(Color::new(ColorFormat::Rgb4, data))
+            }
+            // Types without a decoding, including ones this crate does not
+            // know, keep the type byte as it was read.
+            _ => AttributeValue::Raw {
+                data_type: value.data_type,
+                data,
+            },
+        })
+    }
+
+    /// Returns the type byte and data word the value is stored as. The data
+    /// word of a string is its index in the string pool, given as `string`.
+    pub(crate) fn to_raw(&self, string: u32) -> (u8, u32) {
+        let (data_type, data) = match self {
+            AttributeValue::String(_) => (ResourceValueType::String, string),
+            AttributeValue::Reference(id) => (ResourceValueType::Reference, *id),
+            AttributeValue::Boolean(b) => {
+                (ResourceValueType::Boolean, if *b { u32::MAX } else { 0 })
+            }
+            AttributeValue::Integer(i) => {
+                (ResourceValueType::Dec, u32::from_ne_bytes(i.to_ne_bytes()))
+            }
+            AttributeValue::Hex(h) => (ResourceValueType::Hex, *h),
+            AttributeValue::Float(f) => (ResourceValueType::Float, f.to_bits()),
+            AttributeValue::Dimension(data) => (ResourceValueType::Dimension, *data),
+            AttributeValue::Fraction(data) => (ResourceValueType::Fraction, *data),
+            AttributeValue::Color(color) => {
+                let data_type = match color.get_format() {
+                    ColorFormat::Argb8 => ResourceValueType::ColorArgb8,
+                    ColorFormat::Rgb8 => ResourceValueType::ColorRgb8,
+                    ColorFormat::Argb4 => ResourceValueType::ColorArgb4,
+                    ColorFormat::Rgb4 => ResourceValueType::ColorRgb4,
+                };
+                (data_type, color.get_data())
+            }
+            AttributeValue::Raw { data_type, data } => return (*data_type, *data),
+        };
+        (data_type as u8, data)
+    }
+}
+
+impl fmt::Display for AttributeValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AttributeValue::String(s) => write!(f, "{s}"),
+            AttributeValue::Reference(id) => write!(f, "@0x{id:08x}"),
+            AttributeValue::Boolean(b) => write!(f, "{b}"),
+            AttributeValue::Integer(i) => write!(f, "{i}"),
+            AttributeValue::Hex(h) => write!(f, "0x{h:08x}"),
+            AttributeValue::Float(v) => write!(f, "{v}"),
+            AttributeValue::Dimension(data) => {
+                write_complex(f, *data, 1.0, &["px", "dip", "sp", "pt", "in", "mm"])
+            }
+            AttributeValue::Fraction(data) => write_complex(f, *data, 100.0, &["%", "%p"]),
+            AttributeValue::Color(color) => write!(f, "{color}"),
+            AttributeValue::Raw { data_type, data } => {
+                write!(f, "(type 0x{data_type:x})0x{data:x}")
+            }
+        }
+    }
+}
+
+///Enum representing the forms a color can be written in, which decide
+///the type of its value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ColorFormat {
+    ///`#aarrggbb`
+    Argb8,
+    ///`#rrggbb`
+    Rgb8,
+    ///`#argb`
+    Argb4,
+    ///`#rgb`
+    Rgb4,
+}
+
+///A color value along with the form it was written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Color {
+    format: ColorFormat,
+    data: u32,
+}
+
+impl Color {
+    pub(crate) fn new(format: ColorFormat, data: u32) -> Self {
+        Self { format, data }
+    }
+
+    ///Returns the form the color was written in.
+    #[must_use]
+    pub fn get_format(&self) -> ColorFormat {
+        self.format
+    }
+
+    ///Returns the data word of the value, which holds the color as
+    ///`0xAARRGGBB`.
+    #[must_use]
+    pub fn get_data(&self) -> u32 {
+        self.data
+    }
+
+    ///Returns the `(alpha, red, green, blue)` components of the color.
+    ///
+    ///Colors without an alpha component are opaque, and each 4 bit
+    ///component is expanded to 8 bits, so `#f00` is `(255, 255, 0, 0)`.
+    #[must_use]
+    pub fn get_argb(&self) -> (u8, u8, u8, u8) {
+        let [a, r, g, b] = self.data.to_be_bytes();
+        // The build tools store 4 bit components already expanded, only the
+        // low nibble is relied on.
+        let expand = |c: u8| (c & 0xf) * 0x11;
+        match self.format {
+            ColorFormat::Argb8 => (a, r, g, b),
+            ColorFormat::Rgb8 => (0xff, r, g, b),
+            ColorFormat::Argb4 => (expand(a), expand(r), expand(g), expand(b)),
+            ColorFormat::Rgb4 => (0xff, expand(r), expand(g), expand(b)),
+        }
+    }
+}
+
+impl fmt::Display for Color {
+    ///Writes the color as `#AARRGGBB`, whatever form it was written in.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (a, r, g, b) = self.get_argb();
+        write!(f, "#{a:02X}{r:02X}{g:02X}{b:02X}")
+    }
+}
+
+// Logic borrowed from:
+// https://android.googlesource.com/platform/frameworks/base/+/master/libs/androidfw/ResourceTypes.cpp
+const COMPLEX_UNIT_MASK: u32 = 0xf;
+const COMPLEX_RADIX_SHIFT: u32 = 4;
+const COMPLEX_RADIX_MASK: u32 = 0x3;
+const COMPLEX_MANTISSA_MASK: u32 = 0xff_ffff;
+const COMPLEX_MANTISSA_SHIFT: u32 = 8;
+
+/// Reconstructs the float stored in the mantissa and radix of a complex
+/// data word.
+fn complex_to_float(data: u32) -> f32 {
+    const MANTISSA_MULT: f32 = 1.0 / (1 << COMPLEX_MANTISSA_SHIFT) as f32;
+    const RADIX_MULTS: [f32; 4] = [
+        MANTISSA_MULT,
+        1.0 / (1 << 7) as f32 * MANTISSA_MULT,
+        1.0 / (1 << 15) as f32 * MANTISSA_MULT,
+        1.0 / (1 << 23) as f32 * MANTISSA_MULT,
+    ];
+
+    let mantissa = i32::from_ne_bytes(
+        (data & (COMPLEX_MANTISSA_MASK << COMPLEX_MANTISSA_SHIFT)).to_ne_bytes(),
+    );
+    let radix = (data >> COMPLEX_RADIX_SHIFT) & COMPLEX_RADIX_MASK;
+
+    mantissa as f32 * RADIX_MULTS[radix as usize]
+}
+
+fn write_complex(f: &mut fmt::Formatter<'_>, data: u32, scale: f32, units: &[&str]) -> fmt::Result {
+    let value = complex_to_float(data) * scale;
+    let unit = data & COMPLEX_UNIT_MASK;
+
+    match units.get(unit as usize) {
+        Some(unit) => write!(f, "{value}{unit}"),
+        None => write!(f, "{value} (unit 0x{unit:x})"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dimension_display() {
+        assert_eq!(AttributeValue::Dimension(0x0000_0140).to_string(), "1px");
+        assert_eq!(AttributeValue::Dimension(0x0000_1001).to_string(), "16dip");
+        assert_eq!(AttributeValue::Dimension(0x0000_0e02).to_string(), "14sp");
+        assert_eq!(AttributeValue::Dimension(0x0000_8011).to_string(), "1dip");
+        assert_eq!(AttributeValue::Dimension(0xffff_ff01).to_string(), "-1dip");
+    }
+
+    #[test]
+    fn test_colors() {
+        let color = Color::new(ColorFormat::Argb8, 0x80ff_8000);
+        assert_eq!(color.get_argb(), (0x80, 0xff, 0x80, 0x00));
+        assert_eq!(AttributeValue::Color(color).to_string(), "#80FF8000");
+
+        // Rgb8 colors are opaque even if the alpha byte is not set.
+        let color = Color::new(ColorFormat::Rgb8, 0x0012_3456);
+        assert_eq!(color.get_argb(), (0xff, 0x12, 0x34, 0x56));
+        assert_eq!(color.to_string(), "#FF123456");
+
+        // 4 bit components are expanded from the low nibble of each byte.
+        let color = Color::new(ColorFormat::Argb4, 0x080f_0a0c);
+        assert_eq!(color.get_argb(), (0x88, 0xff, 0xaa, 0xcc));
+        assert_eq!(color.to_string(), "#88FFAACC");
+        let color = Color::new(ColorFormat::Argb4, 0x88ff_ccff);
+        assert_eq!(color.get_argb(), (0x88, 0xff, 0xcc, 0xff));
+
+        let color = Color::new(ColorFormat::Rgb4, 0x000f_0a0c);
+        assert_eq!(color.get_argb(), (0xff, 0xff, 0xaa, 0xcc));
+        assert_eq!(color.to_string(), "#FFFFAACC");
+        assert_eq!(
+            Color::new(ColorFormat::Rgb4, 0xffff_aacc).get_argb(),
+            color.get_argb()
+        );
+    }
+
+    #[test]
+    fn test_fraction_display() {
+        assert_eq!(AttributeValue::Fraction(0x0000_4010).to_string(), "50%");
+        assert_eq!(AttributeValue::Fraction(0x0000_8011).to_string(), "100%p");
+    }
+}
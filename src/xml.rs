@@ -1,76 +1,101 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::ops::Range;
 use indexmap::IndexMap;
-use std::rc::Rc;
 
-use crate::binaryxml::{
-    BinaryXmlDocument, XmlCdata, XmlNodeType, XmlStartElement, XmlStartNameSpace,
-};
-use crate::stringpool::StringPool;
+use crate::binaryxml::{ResourceMap, ResourceValueType, XmlCdata, XmlStartElement};
+use crate::diagnostic::Diagnostic;
+use crate::manifest::ANDROID_NAMESPACE;
+use crate::reader::{XmlEvent, XmlEventReader};
+use crate::resources::FrameworkResources;
+use crate::stringpool::{to_usize, StringEncoding, StringPool};
+use crate::value::AttributeValue;
 use crate::ParseError;
 
 ///Struct representing a parsed XML document.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct XmlDocument {
-    root: Option<Node>,
+    pub(crate) root: Option<Node>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) string_pool: StringPool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) resource_map: ResourceMap,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    chunk_stats: BTreeMap<u16, usize>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    truncated: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) framework_resources: FrameworkResources,
 }
 
 impl XmlDocument {
-    pub(crate) fn new(binaryxml: BinaryXmlDocument) -> Result<Self, ParseError> {
-        let string_pool = binaryxml.string_pool;
-        let resource_map = binaryxml.resource_map;
-
-        let mut namespaces = IndexMap::new();
-
-        // There are some files without the XmlStartNameSpace element.
-        // We should assume that the android namespace is always present even
-        // if not explicitly defined in the document.
-        //
-        // examples/AndroidManifestNoNamespace.xml
-        namespaces.insert(
-            Rc::new("http://schemas.android.com/apk/res/android".to_string()),
-            Rc::new("android".to_string()),
-        );
-
+    pub(crate) fn new(mut reader: XmlEventReader) -> Result<(Self, Vec<Diagnostic>), ParseError> {
+        let mut root = None;
         let mut element_tracker: Vec<Element> = Vec::new();
-        for node in binaryxml.elements {
-            match node.element {
-                XmlNodeType::XmlStartNameSpace(e) => {
-                    let (uri, prefix) = Self::process_start_namespace(&e, &string_pool)?;
-                    namespaces.insert(uri.clone(), prefix.clone());
-                }
-                XmlNodeType::XmlEndNameSpace(_) => {}
-                XmlNodeType::XmlStartElement(e) => {
-                    element_tracker.push(Self::process_start_element(
-                        &e,
-                        &string_pool,
-                        &namespaces,
-                        &resource_map.resource_ids,
-                    )?);
-                }
-                XmlNodeType::XmlEndElement(_) => {
-                    let e = element_tracker.pop().unwrap();
+        for event in &mut reader {
+            match event? {
+                XmlEvent::StartElement(e) => element_tracker.push(e),
+                XmlEvent::EndElement { line_number, .. } => {
+                    let mut e = element_tracker
+                        .pop()
+                        .ok_or(ParseError::UnbalancedElements)?;
+                    e.end_line_number = line_number;
 
-                    if element_tracker.is_empty() {
-                        return Ok(XmlDocument {
-                            root: Some(Node::Element(e)),
-                        });
+                    match element_tracker.last_mut() {
+                        Some(parent) => parent.insert_children(Node::Element(e)),
+                        None => {
+                            root = Some(Node::Element(e));
+                            break;
+                        }
                     }
-
-                    element_tracker
-                        .last_mut()
-                        .unwrap()
-                        .insert_children(Node::Element(e));
                 }
-                XmlNodeType::XmlCdata(e) => {
-                    let cdata = Self::process_cdata(&e, &string_pool)?;
-                    element_tracker
-                        .last_mut()
-                        .unwrap()
-                        .insert_children(Node::Cdata(cdata));
+                // Text outside the root element is dropped like comments.
+                XmlEvent::Cdata(cdata) => {
+                    if let Some(e) = element_tracker.last_mut() {
+                        e.insert_children(Node::Cdata(cdata));
+                    }
+                }
+                // The tree has no place for comments outside the root
+                // element, those are only available as events.
+                XmlEvent::Comment(comment) => {
+                    if let Some(e) = element_tracker.last_mut() {
+                        e.insert_children(Node::Comment(comment));
+                    }
                 }
             };
         }
 
-        Ok(Self { root: None })
+        // Surface errors in the chunks that follow the root element, such
+        // as a truncated end of namespace.
+        for event in &mut reader {
+            event?;
+        }
+
+        // Elements are only left open when collecting diagnostics, keep
+        // what was read of them.
+        while let Some(e) = element_tracker.pop() {
+            match element_tracker.last_mut() {
+                Some(parent) => parent.insert_children(Node::Element(e)),
+                None => root = Some(Node::Element(e)),
+            }
+        }
+
+        let truncated = reader.is_truncated();
+        let (string_pool, resource_map, chunk_stats, framework_resources, diagnostics) =
+            reader.into_parts();
+        let document = Self {
+            root,
+            string_pool,
+            resource_map,
+            chunk_stats,
+            truncated,
+            framework_resources,
+        };
+        Ok((document, diagnostics))
     }
 
     ///Returns the root [Element] of the XML document.
@@ -79,73 +104,167 @@ impl XmlDocument {
         &self.root
     }
 
-    fn process_cdata(e: &XmlCdata, string_pool: &StringPool) -> Result<Cdata, ParseError> {
+    ///Consumes the document and returns its root node, which owns the
+    ///rest of the tree.
+    ///
+    ///Use [`XmlDocument::get_strings`] and friends before calling this if
+    ///the string pool or resource map are still needed.
+    #[must_use]
+    pub fn into_tree(self) -> Option<Node> {
+        self.root
+    }
+
+    ///Returns the number of chunks of each type in the parsed input, keyed
+    ///by the types in [`chunk_type`](crate::chunk_type).
+    ///```rust
+    ///use axmldecoder::chunk_type::RES_XML_START_ELEMENT_TYPE;
+    ///# use axmldecoder::ParseError;
+    ///let data = include_bytes!("../examples/AndroidManifest.xml");
+    ///let xml = axmldecoder::parse(data)?;
+    ///assert_eq!(xml.get_chunk_stats()[&RES_XML_START_ELEMENT_TYPE], 6);
+    ///# Ok::<(), ParseError>(())
+    ///```
+    #[must_use]
+    pub fn get_chunk_stats(&self) -> &BTreeMap<u16, usize> {
+        &self.chunk_stats
+    }
+
+    ///Returns whether the input ended before the size given in the
+    ///document header, in which case the document only holds what was
+    ///read up to that point.
+    ///
+    ///Only [`parse_with_diagnostics`](crate::parse_with_diagnostics)
+    ///returns truncated documents, the other parse functions fail
+    ///instead.
+    #[must_use]
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    ///Returns an iterator over every string in the document's string pool,
+    ///in pool order.
+    pub fn get_strings(&self) -> impl Iterator<Item = &str> {
+        self.string_pool.get_strings()
+    }
+
+    ///Returns the Android framework resource ID mapped to the attribute
+    ///name at `string_index` in the string pool, if any.
+    ///
+    ///Attributes defined by the Android framework have a resource ID,
+    ///while custom attributes do not.
+    #[must_use]
+    pub fn get_attribute_resource_id(&self, string_index: usize) -> Option<u32> {
+        self.resource_map.get(string_index)
+    }
+
+    ///Returns the index of `s` in the document's string pool, or `None`
+    ///if the pool does not contain it.
+    #[must_use]
+    pub fn get_string_index(&self, s: &str) -> Option<usize> {
+        self.string_pool.index_of(s)
+    }
+
+    ///Returns the number of strings in the document's string pool.
+    #[must_use]
+    pub fn get_string_count(&self) -> usize {
+        self.string_pool.len()
+    }
+
+    ///Returns the encoding of the strings in the document's string pool.
+    #[must_use]
+    pub fn get_string_encoding(&self) -> StringEncoding {
+        self.string_pool.encoding
+    }
+
+    pub(crate) fn process_cdata(
+        e: &XmlCdata,
+        string_pool: &StringPool,
+    ) -> Result<Cdata, ParseError> {
+        let typed_value = match e.typed_data.get_type() {
+            Some(ResourceValueType::Null) => None,
+            _ => Some(AttributeValue::new(&e.typed_data, string_pool)?),
+        };
+
         Ok(Cdata {
             data: string_pool
-                .get(usize::try_from(e.data).unwrap())
+                .get(to_usize(e.data)?)
                 .ok_or(ParseError::StringNotFound(e.data))?
                 .to_string(),
+            typed_value,
+            line_number: 0,
+            byte_range: 0..0,
         })
     }
 
-    fn process_start_namespace(
-        e: &XmlStartNameSpace,
+    pub(crate) fn process_namespace(
+        uri: u32,
+        prefix: u32,
         string_pool: &StringPool,
-    ) -> Result<(Rc<String>, Rc<String>), ParseError> {
-        let uri = string_pool
-            .get(usize::try_from(e.uri).unwrap())
-            .ok_or(ParseError::StringNotFound(e.uri))?;
-        let prefix = string_pool
-            .get(usize::try_from(e.prefix).unwrap())
-            .ok_or(ParseError::StringNotFound(e.prefix))?;
-
-        Ok((uri, prefix))
+    ) -> Result<(Arc<String>, Arc<String>), ParseError> {
+        let uri_str = string_pool
+            .get(to_usize(uri)?)
+            .ok_or(ParseError::StringNotFound(uri))?;
+        let prefix_str = string_pool
+            .get(to_usize(prefix)?)
+            .ok_or(ParseError::StringNotFound(prefix))?;
+
+        Ok((uri_str, prefix_str))
     }
 
-    fn process_start_element(
+    pub(crate) fn process_start_element(
         e: &XmlStartElement,
         string_pool: &StringPool,
-        namespaces: &IndexMap<Rc<String>, Rc<String>>,
-        resource_map: &[u32],
+        namespaces: &[(Arc<String>, Arc<String>)],
+        resource_map: &ResourceMap,
+        resources: &FrameworkResources,
     ) -> Result<Element, ParseError> {
         let name = string_pool
-            .get(usize::try_from(e.attr_ext.name).unwrap())
+            .get(to_usize(e.attr_ext.name)?)
             .ok_or(ParseError::StringNotFound(e.attr_ext.name))?;
-        let name = (*name).clone();
 
-        let mut attributes: IndexMap<String, String> = IndexMap::new();
+        let mut attributes = AttributeMap::default();
+        let mut typed_attributes = Vec::with_capacity(e.attributes.len());
 
         // Specially handle the <manifest> element by adding the namespace
         // attributes to it.
-        if name == "manifest" {
-            for (url, name) in namespaces.iter() {
+        if name.as_str() == "manifest" {
+            for (url, name) in namespaces {
                 attributes.insert(format!("xmlns:{}", name), url.to_string());
             }
         }
 
         for attr in &e.attributes {
-            let ns = string_pool.get(usize::try_from(attr.ns).unwrap());
+            let ns = string_pool.get(to_usize(attr.ns)?);
             let name = string_pool
-                .get(usize::try_from(attr.name).unwrap())
+                .get(to_usize(attr.name)?)
                 .ok_or(ParseError::StringNotFound(attr.name))?;
-            let value = attr.typed_value.get_value(string_pool);
+            let value = AttributeValue::new(&attr.typed_value, string_pool)?;
+            let raw_value = match attr.raw_value {
+                u32::MAX => None,
+                i => Some(
+                    string_pool
+                        .get(to_usize(i)?)
+                        .ok_or(ParseError::StringNotFound(i))?,
+                ),
+            };
+
+            let resource_id = resource_map.get(to_usize(attr.name)?);
 
             let mut final_name = String::new();
             if name.is_empty() {
-                let resource_id = resource_map
-                    .get(usize::try_from(attr.name).unwrap())
-                    .ok_or(ParseError::ResourceIdNotFound(attr.name))?;
-                let resource_str = get_resource_string(*resource_id)
-                    .ok_or(ParseError::UnknownResourceString(*resource_id))?;
-                final_name.push_str(&resource_str);
+                let resource_id = resource_id.ok_or(ParseError::ResourceIdNotFound(attr.name))?;
+                let resource_str = resources
+                    .name_for_id(resource_id)
+                    .ok_or(ParseError::UnknownResourceString(resource_id))?;
+                final_name.push_str(resource_str);
             } else {
-                if let Some(n) = ns {
+                if let Some(n) = &ns {
                     // There are samples where the namespace value is the
                     // raw string instead of a URI found in a namespace chunk.
                     // For now, skip appending the namespace for those cases.
                     //
                     // examples/AndroidManifestUnknownNamespace
-                    if let Some(n) = namespaces.get(&n) {
+                    if let Some(n) = lookup_prefix(namespaces, n) {
                         final_name.push_str(n);
                         final_name.push(':');
                     };
@@ -153,11 +272,28 @@ impl XmlDocument {
                 final_name.push_str(&name);
             }
 
-            attributes.insert(final_name, value.to_string());
+            attributes.insert(final_name.clone(), value.to_string());
+            typed_attributes.push(Attribute {
+                name: final_name,
+                namespace: ns,
+                local_name: name,
+                resource_id,
+                raw_value,
+                value,
+                raw_type: attr.typed_value.data_type,
+                raw_data: attr.typed_value.data,
+                byte_range: 0..0,
+            });
         }
 
         Ok(Element {
             attributes,
+            typed_attributes,
+            namespace_declarations: Vec::new(),
+            namespaces: namespaces.to_vec(),
+            line_number: 0,
+            end_line_number: 0,
+            byte_range: 0..0,
             tag: name,
             children: Vec::new(),
         })
@@ -165,48 +301,420 @@ impl XmlDocument {
 }
 
 ///Enum representing possible nodes within the parsed XML document.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum Node {
     Element(Element),
     Cdata(Cdata),
+    Comment(Comment),
+}
+
+///Map of attribute names to their string values, in document order.
+#[cfg(feature = "std")]
+pub type AttributeMap = IndexMap<String, String>;
+
+///Map of attribute names to their string values, in document order.
+#[cfg(not(feature = "std"))]
+pub type AttributeMap = IndexMap<String, String, core::hash::BuildHasherDefault<AttributeHasher>>;
+
+///FNV-1a hasher used by [`AttributeMap`] when `std`, and with it a
+///randomly seeded hasher, is not available.
+#[cfg(not(feature = "std"))]
+#[derive(Debug, Clone, Copy)]
+pub struct AttributeHasher(u64);
+
+#[cfg(not(feature = "std"))]
+impl Default for AttributeHasher {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl core::hash::Hasher for AttributeHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
 }
 
 ///Struct representing an element within the parsed XML document.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Element {
-    attributes: IndexMap<String, String>,
-    tag: String,
-    children: Vec<Node>,
+    attributes: AttributeMap,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) typed_attributes: Vec<Attribute>,
+    /// `(uri, prefix)` pairs of the namespaces declared right before this
+    /// element.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) namespace_declarations: Vec<(Arc<String>, Arc<String>)>,
+    /// `(uri, prefix)` pairs of the namespaces in scope for this element.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    namespaces: Vec<(Arc<String>, Arc<String>)>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) line_number: u32,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) end_line_number: u32,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) byte_range: Range<usize>,
+    tag: Arc<String>,
+    pub(crate) children: Vec<Node>,
 }
 
 impl Element {
+    /// Creates an element with no attributes or children.
+    pub(crate) fn new(tag: Arc<String>) -> Self {
+        Self {
+            attributes: AttributeMap::default(),
+            typed_attributes: Vec::new(),
+            namespace_declarations: Vec::new(),
+            namespaces: Vec::new(),
+            line_number: 0,
+            end_line_number: 0,
+            byte_range: 0..0,
+            tag,
+            children: Vec::new(),
+        }
+    }
+
     ///Returns a map of attributes associated with the element.
     #[must_use]
-    pub fn get_attributes(&self) -> &IndexMap<String, String> {
+    pub fn get_attributes(&self) -> &AttributeMap {
         &self.attributes
     }
 
+    ///Returns the attributes of the element along with their typed values,
+    ///in document order.
+    #[must_use]
+    pub fn get_typed_attributes(&self) -> &[Attribute] {
+        &self.typed_attributes
+    }
+
+    ///Returns the attribute with the qualified name `name`, such as
+    ///`android:name` or `package`.
+    #[must_use]
+    pub fn get_attribute(&self, name: &str) -> Option<&Attribute> {
+        self.typed_attributes.iter().find(|a| a.name == name)
+    }
+
+    ///Returns the attribute with the local name `local_name` in the
+    ///namespace `namespace_uri`, or in no namespace if it is `None`.
+    #[must_use]
+    pub fn get_attribute_ns(
+        &self,
+        namespace_uri: Option<&str>,
+        local_name: &str,
+    ) -> Option<&Attribute> {
+        self.typed_attributes.iter().find(|a| {
+            a.namespace.as_deref().map(String::as_str) == namespace_uri
+                && a.local_name.as_str() == local_name
+        })
+    }
+
+    ///Returns the attributes of the element keyed by their qualified
+    ///name.
+    #[must_use]
+    pub fn get_attribute_map(&self) -> BTreeMap<&str, &Attribute> {
+        self.typed_attributes
+            .iter()
+            .map(|a| (a.name.as_str(), a))
+            .collect()
+    }
+
+    ///Returns the URI bound to `prefix` in the scope of this element.
+    #[must_use]
+    pub fn get_namespace_uri(&self, prefix: &str) -> Option<&str> {
+        self.namespaces
+            .iter()
+            .rev()
+            .find(|(_, p)| p.as_str() == prefix)
+            .map(|(uri, _)| uri.as_str())
+    }
+
+    ///Returns an iterator over the `(prefix, uri)` namespace declarations
+    ///in scope for this element, from outermost to innermost.
+    pub fn get_namespaces(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.namespaces
+            .iter()
+            .map(|(uri, prefix)| (prefix.as_str(), uri.as_str()))
+    }
+
+    ///Returns an iterator over the `(prefix, uri)` namespace declarations
+    ///made on this element.
+    pub fn get_namespace_declarations(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.namespace_declarations
+            .iter()
+            .map(|(uri, prefix)| (prefix.as_str(), uri.as_str()))
+    }
+
     ///Returns the element tag.
     #[must_use]
     pub fn get_tag(&self) -> &str {
         &self.tag
     }
 
+    ///Returns the element tag as it is stored in the string pool. Elements
+    ///with the same tag share the string.
+    #[must_use]
+    pub fn get_shared_tag(&self) -> &Arc<String> {
+        &self.tag
+    }
+
+    ///Returns the line number of the start tag in the original source XML.
+    ///
+    ///Nodes synthesized by the build tools may report line 0.
+    #[must_use]
+    pub fn get_line_number(&self) -> u32 {
+        self.line_number
+    }
+
+    ///Returns the line number of the end tag in the original source XML.
+    #[must_use]
+    pub fn get_end_line_number(&self) -> u32 {
+        self.end_line_number
+    }
+
+    ///Returns the range of bytes of the start element chunk in the parsed
+    ///input.
+    ///
+    ///Elements that were not parsed from a document have an empty range.
+    #[must_use]
+    pub fn get_byte_range(&self) -> Range<usize> {
+        self.byte_range.clone()
+    }
+
     ///Returns a list of child nodes.
     #[must_use]
     pub fn get_children(&self) -> &Vec<Node> {
         &self.children
     }
 
+    ///Returns an iterator over the child elements with the tag `tag`.
+    pub fn children_named<'a: 'b, 'b>(
+        &'a self,
+        tag: &'b str,
+    ) -> impl Iterator<Item = &'a Element> + 'b {
+        self.children.iter().filter_map(move |child| match child {
+            Node::Element(e) if e.tag.as_str() == tag => Some(e),
+            _ => None,
+        })
+    }
+
+    ///Returns the first descendant element matching `path`, a list of tags
+    ///separated by `/` relative to this element, such as
+    ///`application/activity`.
+    #[must_use]
+    pub fn find(&self, path: &str) -> Option<&Element> {
+        let (tag, rest) = match path.split_once('/') {
+            Some((tag, rest)) => (tag, Some(rest)),
+            None => (path, None),
+        };
+        let mut children = self.children_named(tag);
+        match rest {
+            Some(rest) => children.find_map(|child| child.find(rest)),
+            None => children.next(),
+        }
+    }
+
+    /// Replaces the value of the attribute `local_name` in `namespace`, or
+    /// adds it if the element does not have one. A new attribute in the
+    /// Android namespace gets its resource ID from `resources`, and
+    /// `string` is the string pool index of a string value.
+    pub(crate) fn set_attribute(
+        &mut self,
+        namespace: Option<&str>,
+        local_name: &str,
+        value: AttributeValue,
+        string: u32,
+        resources: &FrameworkResources,
+    ) {
+        let existing = self.typed_attributes.iter_mut().find(|a| {
+            (a.get_namespace() == namespace && a.get_local_name() == local_name)
+                // Attributes named through the resource map only have a
+                // qualified name.
+                || (a.local_name.is_empty() && a.name == local_name)
+        });
+
+        let attribute = match existing {
+            Some(attribute) => attribute,
+            None => {
+                let mut attribute = Attribute::new(
+                    namespace.map(|ns| Arc::new(ns.to_string())),
+                    Arc::new(local_name.to_string()),
+                    value.clone(),
+                );
+                if let Some(prefix) = namespace.and_then(|ns| lookup_prefix(&self.namespaces, ns)) {
+                    attribute.name = format!("{prefix}:{local_name}");
+                }
+                if namespace == Some(ANDROID_NAMESPACE) {
+                    attribute.resource_id = resources.id_for_name(local_name);
+                }
+                self.typed_attributes.push(attribute);
+                self.typed_attributes.last_mut().unwrap()
+            }
+        };
+
+        // The value was not read from the input, so it has no raw string
+        // or position.
+        attribute.raw_value = None;
+        (attribute.raw_type, attribute.raw_data) = value.to_raw(string);
+        attribute.byte_range = 0..0;
+        self.attributes
+            .insert(attribute.name.clone(), value.to_string());
+        attribute.value = value;
+    }
+
     fn insert_children(&mut self, child: Node) {
         self.children.push(child);
     }
 }
 
+// The byte range is left out as it depends on the layout of the document,
+// which is rebuilt when it is encoded.
+impl PartialEq for Element {
+    fn eq(&self, other: &Self) -> bool {
+        self.attributes == other.attributes
+            && self.typed_attributes == other.typed_attributes
+            && self.namespace_declarations == other.namespace_declarations
+            && self.namespaces == other.namespaces
+            && self.line_number == other.line_number
+            && self.end_line_number == other.end_line_number
+            && self.tag == other.tag
+            && self.children == other.children
+    }
+}
+
+///Struct representing an attribute of an element within the parsed XML
+///document.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Attribute {
+    name: String,
+    pub(crate) namespace: Option<Arc<String>>,
+    pub(crate) local_name: Arc<String>,
+    pub(crate) resource_id: Option<u32>,
+    raw_value: Option<Arc<String>>,
+    value: AttributeValue,
+    raw_type: u8,
+    raw_data: u32,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) byte_range: Range<usize>,
+}
+
+impl Attribute {
+    /// Creates an attribute without a resource ID. The qualified name is the
+    /// local name, since no prefix is known yet, and the raw value is only
+    /// known once the attribute has been encoded.
+    pub(crate) fn new(
+        namespace: Option<Arc<String>>,
+        local_name: Arc<String>,
+        value: AttributeValue,
+    ) -> Self {
+        Self {
+            name: local_name.to_string(),
+            namespace,
+            local_name,
+            resource_id: None,
+            raw_value: None,
+            value,
+            raw_type: 0,
+            raw_data: 0,
+            byte_range: 0..0,
+        }
+    }
+
+    ///Returns the attribute name, prefixed with its namespace if any.
+    #[must_use]
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    ///Returns the namespace URI of the attribute, if any.
+    #[must_use]
+    pub fn get_namespace(&self) -> Option<&str> {
+        self.namespace.as_deref().map(String::as_str)
+    }
+
+    ///Returns the attribute name without a namespace prefix. This is empty
+    ///for attributes only named through their resource ID.
+    #[must_use]
+    pub fn get_local_name(&self) -> &str {
+        &self.local_name
+    }
+
+    ///Returns the original string value of the attribute, if the document
+    ///kept one.
+    ///
+    ///The build tools keep the string for string attributes, and
+    ///sometimes for other types alongside the typed value.
+    #[must_use]
+    pub fn get_raw_value(&self) -> Option<&str> {
+        self.raw_value.as_deref().map(String::as_str)
+    }
+
+    ///Returns the decoded typed value of the attribute.
+    #[must_use]
+    pub fn get_value(&self) -> &AttributeValue {
+        &self.value
+    }
+
+    ///Returns the type byte of the attribute's value exactly as it was
+    ///read, such as `0x03` for a string or `0x10` for a decimal integer.
+    #[must_use]
+    pub fn get_raw_type(&self) -> u8 {
+        self.raw_type
+    }
+
+    ///Returns the data word of the attribute's value exactly as it was
+    ///read. For strings this is the index into the string pool, or
+    ///`u32::MAX` for a string set with [`XmlDocument::set_attribute`] that
+    ///is not in the pool.
+    #[must_use]
+    pub fn get_raw_data(&self) -> u32 {
+        self.raw_data
+    }
+
+    ///Returns the range of bytes of the attribute in the parsed input. The
+    ///data word of the value, see [`Attribute::get_raw_data`], is the last
+    ///4 bytes of the range.
+    ///
+    ///Attributes that were not parsed from a document have an empty range.
+    #[must_use]
+    pub fn get_byte_range(&self) -> Range<usize> {
+        self.byte_range.clone()
+    }
+}
+
+// The raw value and byte range are left out as they depend on the layout
+// of the document, which is rebuilt when it is encoded.
+impl PartialEq for Attribute {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.namespace == other.namespace
+            && self.local_name == other.local_name
+            && self.resource_id == other.resource_id
+            && self.value == other.value
+    }
+}
+
 ///Struct representing a Cdata element within the parsed XML document.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Cdata {
-    data: String,
+    pub(crate) data: String,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) typed_value: Option<AttributeValue>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) line_number: u32,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) byte_range: Range<usize>,
 }
 
 impl Cdata {
@@ -214,1344 +722,65 @@ impl Cdata {
     pub fn get_data(&self) -> &str {
         &self.data
     }
+
+    ///Returns the typed value stored alongside the text, or `None` if the
+    ///text has no typed value.
+    #[must_use]
+    pub fn get_typed_value(&self) -> Option<&AttributeValue> {
+        self.typed_value.as_ref()
+    }
+
+    ///Returns the line number of the text in the original source XML.
+    #[must_use]
+    pub fn get_line_number(&self) -> u32 {
+        self.line_number
+    }
+
+    ///Returns the range of bytes of the text chunk in the parsed input.
+    #[must_use]
+    pub fn get_byte_range(&self) -> Range<usize> {
+        self.byte_range.clone()
+    }
+}
+
+// The byte range is left out, like for elements.
+impl PartialEq for Cdata {
+    fn eq(&self, other: &Self) -> bool {
+        self.data == other.data
+            && self.typed_value == other.typed_value
+            && self.line_number == other.line_number
+    }
+}
+
+///Struct representing a comment within the parsed XML document.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Comment {
+    pub(crate) data: Arc<String>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) line_number: u32,
+}
+
+impl Comment {
+    ///Returns the text of the comment.
+    #[must_use]
+    pub fn get_data(&self) -> &str {
+        &self.data
+    }
+
+    ///Returns the line number of the node the comment is attached to in
+    ///the original source XML.
+    #[must_use]
+    pub fn get_line_number(&self) -> u32 {
+        self.line_number
+    }
 }
 
-// Logic borrowed from:
-// https://github.com/ytsutano/axmldec/blob/master/lib/jitana/util/axml_parser.cpp#L504
-fn get_resource_string(resource_id: u32) -> Option<String> {
-    const RESOURCE_STRINGS: &[&str] = &[
-        "theme",
-        "label",
-        "icon",
-        "name",
-        "manageSpaceActivity",
-        "allowClearUserData",
-        "permission",
-        "readPermission",
-        "writePermission",
-        "protectionLevel",
-        "permissionGroup",
-        "sharedUserId",
-        "hasCode",
-        "persistent",
-        "enabled",
-        "debuggable",
-        "exported",
-        "process",
-        "taskAffinity",
-        "multiprocess",
-        "finishOnTaskLaunch",
-        "clearTaskOnLaunch",
-        "stateNotNeeded",
-        "excludeFromRecents",
-        "authorities",
-        "syncable",
-        "initOrder",
-        "grantUriPermissions",
-        "priority",
-        "launchMode",
-        "screenOrientation",
-        "configChanges",
-        "description",
-        "targetPackage",
-        "handleProfiling",
-        "functionalTest",
-        "value",
-        "resource",
-        "mimeType",
-        "scheme",
-        "host",
-        "port",
-        "path",
-        "pathPrefix",
-        "pathPattern",
-        "action",
-        "data",
-        "targetClass",
-        "colorForeground",
-        "colorBackground",
-        "backgroundDimAmount",
-        "disabledAlpha",
-        "textAppearance",
-        "textAppearanceInverse",
-        "textColorPrimary",
-        "textColorPrimaryDisableOnly",
-        "textColorSecondary",
-        "textColorPrimaryInverse",
-        "textColorSecondaryInverse",
-        "textColorPrimaryNoDisable",
-        "textColorSecondaryNoDisable",
-        "textColorPrimaryInverseNoDisable",
-        "textColorSecondaryInverseNoDisable",
-        "textColorHintInverse",
-        "textAppearanceLarge",
-        "textAppearanceMedium",
-        "textAppearanceSmall",
-        "textAppearanceLargeInverse",
-        "textAppearanceMediumInverse",
-        "textAppearanceSmallInverse",
-        "textCheckMark",
-        "textCheckMarkInverse",
-        "buttonStyle",
-        "buttonStyleSmall",
-        "buttonStyleInset",
-        "buttonStyleToggle",
-        "galleryItemBackground",
-        "listPreferredItemHeight",
-        "expandableListPreferredItemPaddingLeft",
-        "expandableListPreferredChildPaddingLeft",
-        "expandableListPreferredItemIndicatorLeft",
-        "expandableListPreferredItemIndicatorRight",
-        "expandableListPreferredChildIndicatorLeft",
-        "expandableListPreferredChildIndicatorRight",
-        "windowBackground",
-        "windowFrame",
-        "windowNoTitle",
-        "windowIsFloating",
-        "windowIsTranslucent",
-        "windowContentOverlay",
-        "windowTitleSize",
-        "windowTitleStyle",
-        "windowTitleBackgroundStyle",
-        "alertDialogStyle",
-        "panelBackground",
-        "panelFullBackground",
-        "panelColorForeground",
-        "panelColorBackground",
-        "panelTextAppearance",
-        "scrollbarSize",
-        "scrollbarThumbHorizontal",
-        "scrollbarThumbVertical",
-        "scrollbarTrackHorizontal",
-        "scrollbarTrackVertical",
-        "scrollbarAlwaysDrawHorizontalTrack",
-        "scrollbarAlwaysDrawVerticalTrack",
-        "absListViewStyle",
-        "autoCompleteTextViewStyle",
-        "checkboxStyle",
-        "dropDownListViewStyle",
-        "editTextStyle",
-        "expandableListViewStyle",
-        "galleryStyle",
-        "gridViewStyle",
-        "imageButtonStyle",
-        "imageWellStyle",
-        "listViewStyle",
-        "listViewWhiteStyle",
-        "popupWindowStyle",
-        "progressBarStyle",
-        "progressBarStyleHorizontal",
-        "progressBarStyleSmall",
-        "progressBarStyleLarge",
-        "seekBarStyle",
-        "ratingBarStyle",
-        "ratingBarStyleSmall",
-        "radioButtonStyle",
-        "scrollbarStyle",
-        "scrollViewStyle",
-        "spinnerStyle",
-        "starStyle",
-        "tabWidgetStyle",
-        "textViewStyle",
-        "webViewStyle",
-        "dropDownItemStyle",
-        "spinnerDropDownItemStyle",
-        "dropDownHintAppearance",
-        "spinnerItemStyle",
-        "mapViewStyle",
-        "preferenceScreenStyle",
-        "preferenceCategoryStyle",
-        "preferenceInformationStyle",
-        "preferenceStyle",
-        "checkBoxPreferenceStyle",
-        "yesNoPreferenceStyle",
-        "dialogPreferenceStyle",
-        "editTextPreferenceStyle",
-        "ringtonePreferenceStyle",
-        "preferenceLayoutChild",
-        "textSize",
-        "typeface",
-        "textStyle",
-        "textColor",
-        "textColorHighlight",
-        "textColorHint",
-        "textColorLink",
-        "state_focused",
-        "state_window_focused",
-        "state_enabled",
-        "state_checkable",
-        "state_checked",
-        "state_selected",
-        "state_active",
-        "state_single",
-        "state_first",
-        "state_middle",
-        "state_last",
-        "state_pressed",
-        "state_expanded",
-        "state_empty",
-        "state_above_anchor",
-        "ellipsize",
-        "x",
-        "y",
-        "windowAnimationStyle",
-        "gravity",
-        "autoLink",
-        "linksClickable",
-        "entries",
-        "layout_gravity",
-        "windowEnterAnimation",
-        "windowExitAnimation",
-        "windowShowAnimation",
-        "windowHideAnimation",
-        "activityOpenEnterAnimation",
-        "activityOpenExitAnimation",
-        "activityCloseEnterAnimation",
-        "activityCloseExitAnimation",
-        "taskOpenEnterAnimation",
-        "taskOpenExitAnimation",
-        "taskCloseEnterAnimation",
-        "taskCloseExitAnimation",
-        "taskToFrontEnterAnimation",
-        "taskToFrontExitAnimation",
-        "taskToBackEnterAnimation",
-        "taskToBackExitAnimation",
-        "orientation",
-        "keycode",
-        "fullDark",
-        "topDark",
-        "centerDark",
-        "bottomDark",
-        "fullBright",
-        "topBright",
-        "centerBright",
-        "bottomBright",
-        "bottomMedium",
-        "centerMedium",
-        "id",
-        "tag",
-        "scrollX",
-        "scrollY",
-        "background",
-        "padding",
-        "paddingLeft",
-        "paddingTop",
-        "paddingRight",
-        "paddingBottom",
-        "focusable",
-        "focusableInTouchMode",
-        "visibility",
-        "fitsSystemWindows",
-        "scrollbars",
-        "fadingEdge",
-        "fadingEdgeLength",
-        "nextFocusLeft",
-        "nextFocusRight",
-        "nextFocusUp",
-        "nextFocusDown",
-        "clickable",
-        "longClickable",
-        "saveEnabled",
-        "drawingCacheQuality",
-        "duplicateParentState",
-        "clipChildren",
-        "clipToPadding",
-        "layoutAnimation",
-        "animationCache",
-        "persistentDrawingCache",
-        "alwaysDrawnWithCache",
-        "addStatesFromChildren",
-        "descendantFocusability",
-        "layout",
-        "inflatedId",
-        "layout_width",
-        "layout_height",
-        "layout_margin",
-        "layout_marginLeft",
-        "layout_marginTop",
-        "layout_marginRight",
-        "layout_marginBottom",
-        "listSelector",
-        "drawSelectorOnTop",
-        "stackFromBottom",
-        "scrollingCache",
-        "textFilterEnabled",
-        "transcriptMode",
-        "cacheColorHint",
-        "dial",
-        "hand_hour",
-        "hand_minute",
-        "format",
-        "checked",
-        "button",
-        "checkMark",
-        "foreground",
-        "measureAllChildren",
-        "groupIndicator",
-        "childIndicator",
-        "indicatorLeft",
-        "indicatorRight",
-        "childIndicatorLeft",
-        "childIndicatorRight",
-        "childDivider",
-        "animationDuration",
-        "spacing",
-        "horizontalSpacing",
-        "verticalSpacing",
-        "stretchMode",
-        "columnWidth",
-        "numColumns",
-        "src",
-        "antialias",
-        "filter",
-        "dither",
-        "scaleType",
-        "adjustViewBounds",
-        "maxWidth",
-        "maxHeight",
-        "tint",
-        "baselineAlignBottom",
-        "cropToPadding",
-        "textOn",
-        "textOff",
-        "baselineAligned",
-        "baselineAlignedChildIndex",
-        "weightSum",
-        "divider",
-        "dividerHeight",
-        "choiceMode",
-        "itemTextAppearance",
-        "horizontalDivider",
-        "verticalDivider",
-        "headerBackground",
-        "itemBackground",
-        "itemIconDisabledAlpha",
-        "rowHeight",
-        "maxRows",
-        "maxItemsPerRow",
-        "moreIcon",
-        "max",
-        "progress",
-        "secondaryProgress",
-        "indeterminate",
-        "indeterminateOnly",
-        "indeterminateDrawable",
-        "progressDrawable",
-        "indeterminateDuration",
-        "indeterminateBehavior",
-        "minWidth",
-        "minHeight",
-        "interpolator",
-        "thumb",
-        "thumbOffset",
-        "numStars",
-        "rating",
-        "stepSize",
-        "isIndicator",
-        "checkedButton",
-        "stretchColumns",
-        "shrinkColumns",
-        "collapseColumns",
-        "layout_column",
-        "layout_span",
-        "bufferType",
-        "text",
-        "hint",
-        "textScaleX",
-        "cursorVisible",
-        "maxLines",
-        "lines",
-        "height",
-        "minLines",
-        "maxEms",
-        "ems",
-        "width",
-        "minEms",
-        "scrollHorizontally",
-        "password",
-        "singleLine",
-        "selectAllOnFocus",
-        "includeFontPadding",
-        "maxLength",
-        "shadowColor",
-        "shadowDx",
-        "shadowDy",
-        "shadowRadius",
-        "numeric",
-        "digits",
-        "phoneNumber",
-        "inputMethod",
-        "capitalize",
-        "autoText",
-        "editable",
-        "freezesText",
-        "drawableTop",
-        "drawableBottom",
-        "drawableLeft",
-        "drawableRight",
-        "drawablePadding",
-        "completionHint",
-        "completionHintView",
-        "completionThreshold",
-        "dropDownSelector",
-        "popupBackground",
-        "inAnimation",
-        "outAnimation",
-        "flipInterval",
-        "fillViewport",
-        "prompt",
-        "startYear",
-        "endYear",
-        "mode",
-        "layout_x",
-        "layout_y",
-        "layout_weight",
-        "layout_toLeftOf",
-        "layout_toRightOf",
-        "layout_above",
-        "layout_below",
-        "layout_alignBaseline",
-        "layout_alignLeft",
-        "layout_alignTop",
-        "layout_alignRight",
-        "layout_alignBottom",
-        "layout_alignParentLeft",
-        "layout_alignParentTop",
-        "layout_alignParentRight",
-        "layout_alignParentBottom",
-        "layout_centerInParent",
-        "layout_centerHorizontal",
-        "layout_centerVertical",
-        "layout_alignWithParentIfMissing",
-        "layout_scale",
-        "visible",
-        "variablePadding",
-        "constantSize",
-        "oneshot",
-        "duration",
-        "drawable",
-        "shape",
-        "innerRadiusRatio",
-        "thicknessRatio",
-        "startColor",
-        "endColor",
-        "useLevel",
-        "angle",
-        "type",
-        "centerX",
-        "centerY",
-        "gradientRadius",
-        "color",
-        "dashWidth",
-        "dashGap",
-        "radius",
-        "topLeftRadius",
-        "topRightRadius",
-        "bottomLeftRadius",
-        "bottomRightRadius",
-        "left",
-        "top",
-        "right",
-        "bottom",
-        "minLevel",
-        "maxLevel",
-        "fromDegrees",
-        "toDegrees",
-        "pivotX",
-        "pivotY",
-        "insetLeft",
-        "insetRight",
-        "insetTop",
-        "insetBottom",
-        "shareInterpolator",
-        "fillBefore",
-        "fillAfter",
-        "startOffset",
-        "repeatCount",
-        "repeatMode",
-        "zAdjustment",
-        "fromXScale",
-        "toXScale",
-        "fromYScale",
-        "toYScale",
-        "fromXDelta",
-        "toXDelta",
-        "fromYDelta",
-        "toYDelta",
-        "fromAlpha",
-        "toAlpha",
-        "delay",
-        "animation",
-        "animationOrder",
-        "columnDelay",
-        "rowDelay",
-        "direction",
-        "directionPriority",
-        "factor",
-        "cycles",
-        "searchMode",
-        "searchSuggestAuthority",
-        "searchSuggestPath",
-        "searchSuggestSelection",
-        "searchSuggestIntentAction",
-        "searchSuggestIntentData",
-        "queryActionMsg",
-        "suggestActionMsg",
-        "suggestActionMsgColumn",
-        "menuCategory",
-        "orderInCategory",
-        "checkableBehavior",
-        "title",
-        "titleCondensed",
-        "alphabeticShortcut",
-        "numericShortcut",
-        "checkable",
-        "selectable",
-        "orderingFromXml",
-        "key",
-        "summary",
-        "order",
-        "widgetLayout",
-        "dependency",
-        "defaultValue",
-        "shouldDisableView",
-        "summaryOn",
-        "summaryOff",
-        "disableDependentsState",
-        "dialogTitle",
-        "dialogMessage",
-        "dialogIcon",
-        "positiveButtonText",
-        "negativeButtonText",
-        "dialogLayout",
-        "entryValues",
-        "ringtoneType",
-        "showDefault",
-        "showSilent",
-        "scaleWidth",
-        "scaleHeight",
-        "scaleGravity",
-        "ignoreGravity",
-        "foregroundGravity",
-        "tileMode",
-        "targetActivity",
-        "alwaysRetainTaskState",
-        "allowTaskReparenting",
-        "searchButtonText",
-        "colorForegroundInverse",
-        "textAppearanceButton",
-        "listSeparatorTextViewStyle",
-        "streamType",
-        "clipOrientation",
-        "centerColor",
-        "minSdkVersion",
-        "windowFullscreen",
-        "unselectedAlpha",
-        "progressBarStyleSmallTitle",
-        "ratingBarStyleIndicator",
-        "apiKey",
-        "textColorTertiary",
-        "textColorTertiaryInverse",
-        "listDivider",
-        "soundEffectsEnabled",
-        "keepScreenOn",
-        "lineSpacingExtra",
-        "lineSpacingMultiplier",
-        "listChoiceIndicatorSingle",
-        "listChoiceIndicatorMultiple",
-        "versionCode",
-        "versionName",
-        "marqueeRepeatLimit",
-        "windowNoDisplay",
-        "backgroundDimEnabled",
-        "inputType",
-        "isDefault",
-        "windowDisablePreview",
-        "privateImeOptions",
-        "editorExtras",
-        "settingsActivity",
-        "fastScrollEnabled",
-        "reqTouchScreen",
-        "reqKeyboardType",
-        "reqHardKeyboard",
-        "reqNavigation",
-        "windowSoftInputMode",
-        "imeFullscreenBackground",
-        "noHistory",
-        "headerDividersEnabled",
-        "footerDividersEnabled",
-        "candidatesTextStyleSpans",
-        "smoothScrollbar",
-        "reqFiveWayNav",
-        "keyBackground",
-        "keyTextSize",
-        "labelTextSize",
-        "keyTextColor",
-        "keyPreviewLayout",
-        "keyPreviewOffset",
-        "keyPreviewHeight",
-        "verticalCorrection",
-        "popupLayout",
-        "state_long_pressable",
-        "keyWidth",
-        "keyHeight",
-        "horizontalGap",
-        "verticalGap",
-        "rowEdgeFlags",
-        "codes",
-        "popupKeyboard",
-        "popupCharacters",
-        "keyEdgeFlags",
-        "isModifier",
-        "isSticky",
-        "isRepeatable",
-        "iconPreview",
-        "keyOutputText",
-        "keyLabel",
-        "keyIcon",
-        "keyboardMode",
-        "isScrollContainer",
-        "fillEnabled",
-        "updatePeriodMillis",
-        "initialLayout",
-        "voiceSearchMode",
-        "voiceLanguageModel",
-        "voicePromptText",
-        "voiceLanguage",
-        "voiceMaxResults",
-        "bottomOffset",
-        "topOffset",
-        "allowSingleTap",
-        "handle",
-        "content",
-        "animateOnClick",
-        "configure",
-        "hapticFeedbackEnabled",
-        "innerRadius",
-        "thickness",
-        "sharedUserLabel",
-        "dropDownWidth",
-        "dropDownAnchor",
-        "imeOptions",
-        "imeActionLabel",
-        "imeActionId",
-        "UNKNOWN",
-        "imeExtractEnterAnimation",
-        "imeExtractExitAnimation",
-        "tension",
-        "extraTension",
-        "anyDensity",
-        "searchSuggestThreshold",
-        "includeInGlobalSearch",
-        "onClick",
-        "targetSdkVersion",
-        "maxSdkVersion",
-        "testOnly",
-        "contentDescription",
-        "gestureStrokeWidth",
-        "gestureColor",
-        "uncertainGestureColor",
-        "fadeOffset",
-        "fadeDuration",
-        "gestureStrokeType",
-        "gestureStrokeLengthThreshold",
-        "gestureStrokeSquarenessThreshold",
-        "gestureStrokeAngleThreshold",
-        "eventsInterceptionEnabled",
-        "fadeEnabled",
-        "backupAgent",
-        "allowBackup",
-        "glEsVersion",
-        "queryAfterZeroResults",
-        "dropDownHeight",
-        "smallScreens",
-        "normalScreens",
-        "largeScreens",
-        "progressBarStyleInverse",
-        "progressBarStyleSmallInverse",
-        "progressBarStyleLargeInverse",
-        "searchSettingsDescription",
-        "textColorPrimaryInverseDisableOnly",
-        "autoUrlDetect",
-        "resizeable",
-        "required",
-        "accountType",
-        "contentAuthority",
-        "userVisible",
-        "windowShowWallpaper",
-        "wallpaperOpenEnterAnimation",
-        "wallpaperOpenExitAnimation",
-        "wallpaperCloseEnterAnimation",
-        "wallpaperCloseExitAnimation",
-        "wallpaperIntraOpenEnterAnimation",
-        "wallpaperIntraOpenExitAnimation",
-        "wallpaperIntraCloseEnterAnimation",
-        "wallpaperIntraCloseExitAnimation",
-        "supportsUploading",
-        "killAfterRestore",
-        "restoreNeedsApplication",
-        "smallIcon",
-        "accountPreferences",
-        "textAppearanceSearchResultSubtitle",
-        "textAppearanceSearchResultTitle",
-        "summaryColumn",
-        "detailColumn",
-        "detailSocialSummary",
-        "thumbnail",
-        "detachWallpaper",
-        "finishOnCloseSystemDialogs",
-        "scrollbarFadeDuration",
-        "scrollbarDefaultDelayBeforeFade",
-        "fadeScrollbars",
-        "colorBackgroundCacheHint",
-        "dropDownHorizontalOffset",
-        "dropDownVerticalOffset",
-        "quickContactBadgeStyleWindowSmall",
-        "quickContactBadgeStyleWindowMedium",
-        "quickContactBadgeStyleWindowLarge",
-        "quickContactBadgeStyleSmallWindowSmall",
-        "quickContactBadgeStyleSmallWindowMedium",
-        "quickContactBadgeStyleSmallWindowLarge",
-        "author",
-        "autoStart",
-        "expandableListViewWhiteStyle",
-        "installLocation",
-        "vmSafeMode",
-        "webTextViewStyle",
-        "restoreAnyVersion",
-        "tabStripLeft",
-        "tabStripRight",
-        "tabStripEnabled",
-        "logo",
-        "xlargeScreens",
-        "immersive",
-        "overScrollMode",
-        "overScrollHeader",
-        "overScrollFooter",
-        "filterTouchesWhenObscured",
-        "textSelectHandleLeft",
-        "textSelectHandleRight",
-        "textSelectHandle",
-        "textSelectHandleWindowStyle",
-        "popupAnimationStyle",
-        "screenSize",
-        "screenDensity",
-        "allContactsName",
-        "windowActionBar",
-        "actionBarStyle",
-        "navigationMode",
-        "displayOptions",
-        "subtitle",
-        "customNavigationLayout",
-        "hardwareAccelerated",
-        "measureWithLargestChild",
-        "animateFirstView",
-        "dropDownSpinnerStyle",
-        "actionDropDownStyle",
-        "actionButtonStyle",
-        "showAsAction",
-        "previewImage",
-        "actionModeBackground",
-        "actionModeCloseDrawable",
-        "windowActionModeOverlay",
-        "valueFrom",
-        "valueTo",
-        "valueType",
-        "propertyName",
-        "ordering",
-        "fragment",
-        "windowActionBarOverlay",
-        "fragmentOpenEnterAnimation",
-        "fragmentOpenExitAnimation",
-        "fragmentCloseEnterAnimation",
-        "fragmentCloseExitAnimation",
-        "fragmentFadeEnterAnimation",
-        "fragmentFadeExitAnimation",
-        "actionBarSize",
-        "imeSubtypeLocale",
-        "imeSubtypeMode",
-        "imeSubtypeExtraValue",
-        "splitMotionEvents",
-        "listChoiceBackgroundIndicator",
-        "spinnerMode",
-        "animateLayoutChanges",
-        "actionBarTabStyle",
-        "actionBarTabBarStyle",
-        "actionBarTabTextStyle",
-        "actionOverflowButtonStyle",
-        "actionModeCloseButtonStyle",
-        "titleTextStyle",
-        "subtitleTextStyle",
-        "iconifiedByDefault",
-        "actionLayout",
-        "actionViewClass",
-        "activatedBackgroundIndicator",
-        "state_activated",
-        "listPopupWindowStyle",
-        "popupMenuStyle",
-        "textAppearanceLargePopupMenu",
-        "textAppearanceSmallPopupMenu",
-        "breadCrumbTitle",
-        "breadCrumbShortTitle",
-        "listDividerAlertDialog",
-        "textColorAlertDialogListItem",
-        "loopViews",
-        "dialogTheme",
-        "alertDialogTheme",
-        "dividerVertical",
-        "homeAsUpIndicator",
-        "enterFadeDuration",
-        "exitFadeDuration",
-        "selectableItemBackground",
-        "autoAdvanceViewId",
-        "useIntrinsicSizeAsMinimum",
-        "actionModeCutDrawable",
-        "actionModeCopyDrawable",
-        "actionModePasteDrawable",
-        "textEditPasteWindowLayout",
-        "textEditNoPasteWindowLayout",
-        "textIsSelectable",
-        "windowEnableSplitTouch",
-        "indeterminateProgressStyle",
-        "progressBarPadding",
-        "animationResolution",
-        "state_accelerated",
-        "baseline",
-        "homeLayout",
-        "opacity",
-        "alpha",
-        "transformPivotX",
-        "transformPivotY",
-        "translationX",
-        "translationY",
-        "scaleX",
-        "scaleY",
-        "rotation",
-        "rotationX",
-        "rotationY",
-        "showDividers",
-        "dividerPadding",
-        "borderlessButtonStyle",
-        "dividerHorizontal",
-        "itemPadding",
-        "buttonBarStyle",
-        "buttonBarButtonStyle",
-        "segmentedButtonStyle",
-        "staticWallpaperPreview",
-        "allowParallelSyncs",
-        "isAlwaysSyncable",
-        "verticalScrollbarPosition",
-        "fastScrollAlwaysVisible",
-        "fastScrollThumbDrawable",
-        "fastScrollPreviewBackgroundLeft",
-        "fastScrollPreviewBackgroundRight",
-        "fastScrollTrackDrawable",
-        "fastScrollOverlayPosition",
-        "customTokens",
-        "nextFocusForward",
-        "firstDayOfWeek",
-        "showWeekNumber",
-        "minDate",
-        "maxDate",
-        "shownWeekCount",
-        "selectedWeekBackgroundColor",
-        "focusedMonthDateColor",
-        "unfocusedMonthDateColor",
-        "weekNumberColor",
-        "weekSeparatorLineColor",
-        "selectedDateVerticalBar",
-        "weekDayTextAppearance",
-        "dateTextAppearance",
-        "UNKNOWN",
-        "spinnersShown",
-        "calendarViewShown",
-        "state_multiline",
-        "detailsElementBackground",
-        "textColorHighlightInverse",
-        "textColorLinkInverse",
-        "editTextColor",
-        "editTextBackground",
-        "horizontalScrollViewStyle",
-        "layerType",
-        "alertDialogIcon",
-        "windowMinWidthMajor",
-        "windowMinWidthMinor",
-        "queryHint",
-        "fastScrollTextColor",
-        "largeHeap",
-        "windowCloseOnTouchOutside",
-        "datePickerStyle",
-        "calendarViewStyle",
-        "textEditSidePasteWindowLayout",
-        "textEditSideNoPasteWindowLayout",
-        "actionMenuTextAppearance",
-        "actionMenuTextColor",
-        "textCursorDrawable",
-        "resizeMode",
-        "requiresSmallestWidthDp",
-        "compatibleWidthLimitDp",
-        "largestWidthLimitDp",
-        "state_hovered",
-        "state_drag_can_accept",
-        "state_drag_hovered",
-        "stopWithTask",
-        "switchTextOn",
-        "switchTextOff",
-        "switchPreferenceStyle",
-        "switchTextAppearance",
-        "track",
-        "switchMinWidth",
-        "switchPadding",
-        "thumbTextPadding",
-        "textSuggestionsWindowStyle",
-        "textEditSuggestionItemLayout",
-        "rowCount",
-        "rowOrderPreserved",
-        "columnCount",
-        "columnOrderPreserved",
-        "useDefaultMargins",
-        "alignmentMode",
-        "layout_row",
-        "layout_rowSpan",
-        "layout_columnSpan",
-        "actionModeSelectAllDrawable",
-        "isAuxiliary",
-        "accessibilityEventTypes",
-        "packageNames",
-        "accessibilityFeedbackType",
-        "notificationTimeout",
-        "accessibilityFlags",
-        "canRetrieveWindowContent",
-        "listPreferredItemHeightLarge",
-        "listPreferredItemHeightSmall",
-        "actionBarSplitStyle",
-        "actionProviderClass",
-        "backgroundStacked",
-        "backgroundSplit",
-        "textAllCaps",
-        "colorPressedHighlight",
-        "colorLongPressedHighlight",
-        "colorFocusedHighlight",
-        "colorActivatedHighlight",
-        "colorMultiSelectHighlight",
-        "drawableStart",
-        "drawableEnd",
-        "actionModeStyle",
-        "minResizeWidth",
-        "minResizeHeight",
-        "actionBarWidgetTheme",
-        "uiOptions",
-        "subtypeLocale",
-        "subtypeExtraValue",
-        "actionBarDivider",
-        "actionBarItemBackground",
-        "actionModeSplitBackground",
-        "textAppearanceListItem",
-        "textAppearanceListItemSmall",
-        "targetDescriptions",
-        "directionDescriptions",
-        "overridesImplicitlyEnabledSubtype",
-        "listPreferredItemPaddingLeft",
-        "listPreferredItemPaddingRight",
-        "requiresFadingEdge",
-        "publicKey",
-        "parentActivityName",
-        "UNKNOWN",
-        "isolatedProcess",
-        "importantForAccessibility",
-        "keyboardLayout",
-        "fontFamily",
-        "mediaRouteButtonStyle",
-        "mediaRouteTypes",
-        "supportsRtl",
-        "textDirection",
-        "textAlignment",
-        "layoutDirection",
-        "paddingStart",
-        "paddingEnd",
-        "layout_marginStart",
-        "layout_marginEnd",
-        "layout_toStartOf",
-        "layout_toEndOf",
-        "layout_alignStart",
-        "layout_alignEnd",
-        "layout_alignParentStart",
-        "layout_alignParentEnd",
-        "listPreferredItemPaddingStart",
-        "listPreferredItemPaddingEnd",
-        "singleUser",
-        "presentationTheme",
-        "subtypeId",
-        "initialKeyguardLayout",
-        "UNKNOWN",
-        "widgetCategory",
-        "permissionGroupFlags",
-        "labelFor",
-        "permissionFlags",
-        "checkedTextViewStyle",
-        "showOnLockScreen",
-        "format12Hour",
-        "format24Hour",
-        "timeZone",
-        "mipMap",
-        "mirrorForRtl",
-        "windowOverscan",
-        "requiredForAllUsers",
-        "indicatorStart",
-        "indicatorEnd",
-        "childIndicatorStart",
-        "childIndicatorEnd",
-        "restrictedAccountType",
-        "requiredAccountType",
-        "canRequestTouchExplorationMode",
-        "canRequestEnhancedWebAccessibility",
-        "canRequestFilterKeyEvents",
-        "layoutMode",
-        "keySet",
-        "targetId",
-        "fromScene",
-        "toScene",
-        "transition",
-        "transitionOrdering",
-        "fadingMode",
-        "startDelay",
-        "ssp",
-        "sspPrefix",
-        "sspPattern",
-        "addPrintersActivity",
-        "vendor",
-        "category",
-        "isAsciiCapable",
-        "autoMirrored",
-        "supportsSwitchingToNextInputMethod",
-        "requireDeviceUnlock",
-        "apduServiceBanner",
-        "accessibilityLiveRegion",
-        "windowTranslucentStatus",
-        "windowTranslucentNavigation",
-        "advancedPrintOptionsActivity",
-        "banner",
-        "windowSwipeToDismiss",
-        "isGame",
-        "allowEmbedded",
-        "setupActivity",
-        "fastScrollStyle",
-        "windowContentTransitions",
-        "windowContentTransitionManager",
-        "translationZ",
-        "tintMode",
-        "controlX1",
-        "controlY1",
-        "controlX2",
-        "controlY2",
-        "transitionName",
-        "transitionGroup",
-        "viewportWidth",
-        "viewportHeight",
-        "fillColor",
-        "pathData",
-        "strokeColor",
-        "strokeWidth",
-        "trimPathStart",
-        "trimPathEnd",
-        "trimPathOffset",
-        "strokeLineCap",
-        "strokeLineJoin",
-        "strokeMiterLimit",
-        "UNKNOWN",
-        "UNKNOWN",
-        "UNKNOWN",
-        "UNKNOWN",
-        "UNKNOWN",
-        "UNKNOWN",
-        "UNKNOWN",
-        "UNKNOWN",
-        "UNKNOWN",
-        "UNKNOWN",
-        "UNKNOWN",
-        "UNKNOWN",
-        "UNKNOWN",
-        "UNKNOWN",
-        "UNKNOWN",
-        "UNKNOWN",
-        "UNKNOWN",
-        "UNKNOWN",
-        "UNKNOWN",
-        "UNKNOWN",
-        "UNKNOWN",
-        "UNKNOWN",
-        "UNKNOWN",
-        "UNKNOWN",
-        "UNKNOWN",
-        "UNKNOWN",
-        "UNKNOWN",
-        "colorControlNormal",
-        "colorControlActivated",
-        "colorButtonNormal",
-        "colorControlHighlight",
-        "persistableMode",
-        "titleTextAppearance",
-        "subtitleTextAppearance",
-        "slideEdge",
-        "actionBarTheme",
-        "textAppearanceListItemSecondary",
-        "colorPrimary",
-        "colorPrimaryDark",
-        "colorAccent",
-        "nestedScrollingEnabled",
-        "windowEnterTransition",
-        "windowExitTransition",
-        "windowSharedElementEnterTransition",
-        "windowSharedElementExitTransition",
-        "windowAllowReturnTransitionOverlap",
-        "windowAllowEnterTransitionOverlap",
-        "sessionService",
-        "stackViewStyle",
-        "switchStyle",
-        "elevation",
-        "excludeId",
-        "excludeClass",
-        "hideOnContentScroll",
-        "actionOverflowMenuStyle",
-        "documentLaunchMode",
-        "maxRecents",
-        "autoRemoveFromRecents",
-        "stateListAnimator",
-        "toId",
-        "fromId",
-        "reversible",
-        "splitTrack",
-        "targetName",
-        "excludeName",
-        "matchOrder",
-        "windowDrawsSystemBarBackgrounds",
-        "statusBarColor",
-        "navigationBarColor",
-        "contentInsetStart",
-        "contentInsetEnd",
-        "contentInsetLeft",
-        "contentInsetRight",
-        "paddingMode",
-        "layout_rowWeight",
-        "layout_columnWeight",
-        "translateX",
-        "translateY",
-        "selectableItemBackgroundBorderless",
-        "elegantTextHeight",
-        "UNKNOWN",
-        "UNKNOWN",
-        "UNKNOWN",
-        "windowTransitionBackgroundFadeDuration",
-        "overlapAnchor",
-        "progressTint",
-        "progressTintMode",
-        "progressBackgroundTint",
-        "progressBackgroundTintMode",
-        "secondaryProgressTint",
-        "secondaryProgressTintMode",
-        "indeterminateTint",
-        "indeterminateTintMode",
-        "backgroundTint",
-        "backgroundTintMode",
-        "foregroundTint",
-        "foregroundTintMode",
-        "buttonTint",
-        "buttonTintMode",
-        "thumbTint",
-        "thumbTintMode",
-        "fullBackupOnly",
-        "propertyXName",
-        "propertyYName",
-        "relinquishTaskIdentity",
-        "tileModeX",
-        "tileModeY",
-        "actionModeShareDrawable",
-        "actionModeFindDrawable",
-        "actionModeWebSearchDrawable",
-        "transitionVisibilityMode",
-        "minimumHorizontalAngle",
-        "minimumVerticalAngle",
-        "maximumAngle",
-        "searchViewStyle",
-        "closeIcon",
-        "goIcon",
-        "searchIcon",
-        "voiceIcon",
-        "commitIcon",
-        "suggestionRowLayout",
-        "queryBackground",
-        "submitBackground",
-        "buttonBarPositiveButtonStyle",
-        "buttonBarNeutralButtonStyle",
-        "buttonBarNegativeButtonStyle",
-        "popupElevation",
-        "actionBarPopupTheme",
-        "multiArch",
-        "touchscreenBlocksFocus",
-        "windowElevation",
-        "launchTaskBehindTargetAnimation",
-        "launchTaskBehindSourceAnimation",
-        "restrictionType",
-        "dayOfWeekBackground",
-        "dayOfWeekTextAppearance",
-        "headerMonthTextAppearance",
-        "headerDayOfMonthTextAppearance",
-        "headerYearTextAppearance",
-        "yearListItemTextAppearance",
-        "yearListSelectorColor",
-        "calendarTextColor",
-        "recognitionService",
-        "timePickerStyle",
-        "timePickerDialogTheme",
-        "headerTimeTextAppearance",
-        "headerAmPmTextAppearance",
-        "numbersTextColor",
-        "numbersBackgroundColor",
-        "numbersSelectorColor",
-        "amPmTextColor",
-        "amPmBackgroundColor",
-        "UNKNOWN",
-        "checkMarkTint",
-        "checkMarkTintMode",
-        "popupTheme",
-        "toolbarStyle",
-        "windowClipToOutline",
-        "datePickerDialogTheme",
-        "showText",
-        "windowReturnTransition",
-        "windowReenterTransition",
-        "windowSharedElementReturnTransition",
-        "windowSharedElementReenterTransition",
-        "resumeWhilePausing",
-        "datePickerMode",
-        "timePickerMode",
-        "inset",
-        "letterSpacing",
-        "fontFeatureSettings",
-        "outlineProvider",
-        "contentAgeHint",
-        "country",
-        "windowSharedElementsUseOverlay",
-        "reparent",
-        "reparentWithOverlay",
-        "ambientShadowAlpha",
-        "spotShadowAlpha",
-        "navigationIcon",
-        "navigationContentDescription",
-        "fragmentExitTransition",
-        "fragmentEnterTransition",
-        "fragmentSharedElementEnterTransition",
-        "fragmentReturnTransition",
-        "fragmentSharedElementReturnTransition",
-        "fragmentReenterTransition",
-        "fragmentAllowEnterTransitionOverlap",
-        "fragmentAllowReturnTransitionOverlap",
-        "patternPathData",
-        "strokeAlpha",
-        "fillAlpha",
-        "windowActivityTransitions",
-        "colorEdgeEffect",
-        "resizeClip",
-        "collapseContentDescription",
-        "accessibilityTraversalBefore",
-        "accessibilityTraversalAfter",
-        "dialogPreferredPadding",
-        "searchHintIcon",
-        "revisionCode",
-        "drawableTint",
-        "drawableTintMode",
-        "fraction",
-        "trackTint",
-        "trackTintMode",
-        "start",
-        "end",
-        "breakStrategy",
-        "hyphenationFrequency",
-        "allowUndo",
-        "windowLightStatusBar",
-        "numbersInnerTextColor",
-        "colorBackgroundFloating",
-        "titleTextColor",
-        "subtitleTextColor",
-        "thumbPosition",
-        "scrollIndicators",
-        "contextClickable",
-        "fingerprintAuthDrawable",
-        "logoDescription",
-        "extractNativeLibs",
-        "fullBackupContent",
-        "usesCleartextTraffic",
-        "lockTaskMode",
-        "autoVerify",
-        "showForAllUsers",
-        "supportsAssist",
-        "supportsLaunchVoiceAssistFromKeyguard",
-        "listMenuViewStyle",
-        "subMenuArrow",
-        "defaultWidth",
-        "defaultHeight",
-        "resizeableActivity",
-        "supportsPictureInPicture",
-        "titleMargin",
-        "titleMarginStart",
-        "titleMarginEnd",
-        "titleMarginTop",
-        "titleMarginBottom",
-        "maxButtonHeight",
-        "buttonGravity",
-        "collapseIcon",
-        "level",
-        "contextPopupMenuStyle",
-        "textAppearancePopupMenuHeader",
-        "windowBackgroundFallback",
-        "defaultToDeviceProtectedStorage",
-        "directBootAware",
-        "preferenceFragmentStyle",
-        "canControlMagnification",
-        "languageTag",
-        "pointerIcon",
-        "tickMark",
-        "tickMarkTint",
-        "tickMarkTintMode",
-        "canPerformGestures",
-        "externalService",
-        "supportsLocalInteraction",
-        "startX",
-        "startY",
-        "endX",
-        "endY",
-        "offset",
-        "use32bitAbi",
-        "bitmap",
-        "hotSpotX",
-        "hotSpotY",
-        "version",
-        "backupInForeground",
-        "countDown",
-        "canRecord",
-        "tunerCount",
-        "fillType",
-        "popupEnterTransition",
-        "popupExitTransition",
-        "forceHasOverlappingRendering",
-        "contentInsetStartWithNavigation",
-        "contentInsetEndWithActions",
-        "numberPickerStyle",
-        "enableVrMode",
-        "UNKNOWN",
-        "networkSecurityConfig",
-        "shortcutId",
-        "shortcutShortLabel",
-        "shortcutLongLabel",
-        "shortcutDisabledMessage",
-        "roundIcon",
-        "contextUri",
-        "contextDescription",
-        "showMetadataInPreview",
-        "colorSecondary",
-    ];
-
-    let i = resource_id - 0x0101_0000;
-
-    Some((*RESOURCE_STRINGS.get(usize::try_from(i).unwrap())?).to_string())
+/// Returns the prefix of the innermost namespace in scope bound to `uri`.
+fn lookup_prefix<'a>(namespaces: &'a [(Arc<String>, Arc<String>)], uri: &str) -> Option<&'a str> {
+    namespaces
+        .iter()
+        .rev()
+        .find(|(u, _)| u.as_str() == uri)
+        .map(|(_, prefix)| prefix.as_str())
 }